@@ -0,0 +1,1671 @@
+use std::{future::Future, ops::Range, path::{Path, PathBuf}, sync::Arc, time::{Duration, Instant, SystemTime}};
+use iced::widget::text_editor::{self, Action, Edit, Motion};
+
+use crate::{comments::Comment, editor_core::Document, encoding::Encoding, highlight::Language, save_file, search, Error, LineEnding, LoadedFile};
+
+/// an open document, shown as a tab
+pub struct Buffer {
+    pub id: usize,
+    pub path: Option<PathBuf>,
+    pub content: text_editor::Content,
+    pub error: Option<Error>,
+    pub is_dirty: bool,
+    pub stats: Stats,
+    pub line_ending: LineEnding,
+    pub encoding: Encoding,
+    pub language: Option<Language>, // overrides the detected one
+    pub read_only: bool,
+    pub light: bool, // a big file opened read-only without a history, reloading makes it editable
+    pub scratch: bool, // throwaway notes, saved only as copies through Save As
+    pub modified: Option<SystemTime>, // of the file on disk, when it was last read or found unchanged
+    pub project: Option<PathBuf>, // root of the project the file is in, or its folder
+    pub saving: Option<usize>, // generation of the latest save in flight, results of older ones are dropped
+    base: Arc<String>, // text the history is replayed on
+    history: Vec<Action>,
+    undone: Vec<Vec<Action>>,
+    edits: usize, // edits in the history
+    moves_from: usize, // where the motions after the last edit start in the history
+    saved_edits: Option<usize>, // edits in the history when it matched the file, gone once undone past and typed over
+    drag: Option<Drag>,
+    anchor: Option<(usize, usize)>, // where the selection started, the cursor being its other end
+    stale: Option<Stale>,
+    revision: usize, // bumped on every change to the text
+    extra: Vec<(usize, usize)>, // byte ranges of the cursors besides the editor's own
+    extra_revision: usize, // the extra cursors are dropped once the text changes under them
+    block: Option<((usize, usize), (usize, usize))>, // anchor and head of the rectangle the extra cursors make up
+}
+
+/// stats wait for a pause in typing, but not forever
+///
+/// counting on every key cost about 7 ms a keystroke on a 5 MB log and 32 ms
+/// on a 20 MB one (rebuilding the text plus the old word and char count, release
+/// build); now a key costs none of that, and the recount, 14 and 52 ms with the
+/// line stats, runs once per pause
+const STATS_PAUSE: Duration = Duration::from_millis(150);
+const STATS_MAX_DELAY: Duration = Duration::from_secs(1);
+
+/// undo replays the history, so past this many actions the older half is folded into the base
+const MAX_HISTORY: usize = 10_000;
+
+/// word and character count of the buffer
+#[derive(Debug, Clone, Default)]
+pub struct Stats {
+    pub words: usize,
+    pub chars: usize,
+    pub longest_line: usize, // in chars
+    pub lines: Arc<Vec<(usize, usize)>>, // indent and length of each line, for the minimap
+    pub tabs: Arc<Vec<usize>>, // tabs in each line's indent, for the indentation guides
+}
+
+impl Stats {
+    fn of(text: &str) -> Self {
+        Self {
+            words: text.split_whitespace().count(),
+            chars: text.chars().count(), // unicode scalars, not bytes
+            longest_line: text.lines().map(|line| line.chars().count()).max().unwrap_or(0),
+            lines: Arc::new(text.lines().map(|line| {
+                let content = line.trim_start();
+                (line.len() - content.len(), content.trim_end().chars().count())
+            }).collect()),
+            tabs: Arc::new(text.lines().map(|line| {
+                line.chars().take_while(|c| c.is_whitespace()).filter(|&c| c == '\t').count()
+            }).collect()),
+        }
+    }
+}
+
+/// when the stats stopped matching the text
+#[derive(Debug, Clone, Copy)]
+struct Stale {
+    since: Instant, // first edit not counted yet
+    last: Instant,  // latest edit
+}
+
+/// where the current mouse selection started
+struct Drag {
+    mark: usize, // history length before the selection was recorded
+    anchor: (usize, usize),
+}
+
+impl Buffer {
+    /// an empty, unsaved buffer
+    pub fn new(id: usize) -> Self {
+        Self {
+            id,
+            path: None,
+            content: text_editor::Content::new(),
+            error: None,
+            is_dirty: false,
+            stats: Stats::default(),
+            line_ending: LineEnding::default(),
+            encoding: Encoding::default(),
+            language: None,
+            read_only: false,
+            light: false,
+            scratch: false,
+            modified: None,
+            project: None,
+            saving: None,
+            base: Arc::new(String::new()),
+            history: Vec::new(),
+            undone: Vec::new(),
+            edits: 0,
+            moves_from: 0,
+            saved_edits: Some(0),
+            drag: None,
+            anchor: None,
+            stale: None,
+            revision: 0,
+            extra: Vec::new(),
+            extra_revision: 0,
+            block: None,
+        }
+    }
+
+    /// a buffer holding a file read from disk
+    pub fn from_file(id: usize, file: LoadedFile) -> Self {
+        let LoadedFile { path, content, line_ending, encoding, read_only, modified, project } = file;
+
+        Self {
+            path: Some(path),
+            read_only,
+            modified,
+            project: Some(project),
+            ..Self::from_text(id, content, line_ending, encoding)
+        }
+    }
+
+    /// a big file opened read-only, keeping no history so moving around it stays cheap
+    pub fn light(id: usize, file: LoadedFile) -> Self {
+        Self {
+            read_only: true,
+            light: true,
+            ..Self::from_file(id, file)
+        }
+    }
+
+    /// an unsaved buffer starting out with `content`, such as piped input
+    pub fn from_text(id: usize, content: Arc<String>, line_ending: LineEnding, encoding: Encoding) -> Self {
+        let mut buffer = Self {
+            content: text_editor::Content::with(&content),
+            line_ending,
+            encoding,
+            base: content,
+            ..Self::new(id)
+        };
+        buffer.stats = Stats::of(&buffer.base);
+
+        buffer
+    }
+
+    /// swap in a fresh read of the file, keeping the cursor line and language
+    pub fn reload(&mut self, file: LoadedFile) {
+        let language = self.language;
+        let line = self.content.cursor_position().0;
+        let revision = self.revision;
+
+        // a change on disk doesn't give up the read-only mode, making it editable does
+        *self = match self.light {
+            true => Buffer::light(self.id, file),
+            false => Buffer::from_file(self.id, file),
+        };
+        self.language = language;
+        self.revision = revision + 1;
+        self.move_to((line.min(self.content.line_count().saturating_sub(1)), 0));
+    }
+
+    /// empty a buffer that was never saved, keeping what kind of buffer it is
+    pub fn clear(&mut self) {
+        let revision = self.revision;
+
+        *self = Buffer { scratch: self.scratch, language: self.language, ..Buffer::new(self.id) };
+        self.revision = revision + 1;
+    }
+
+    /// the path shown in the status bar, from the project root when there is one
+    pub fn display_path(&self) -> Option<String> {
+        let path = self.path.as_deref()?;
+        let project = self.project.as_deref().filter(|project| !project.as_os_str().is_empty());
+
+        match project.and_then(|project| Some((project.file_name()?, path.strip_prefix(project).ok()?))) {
+            Some((name, relative)) => Some(format!("{} › {}", name.to_string_lossy(), relative.display())),
+            None => Some(path.display().to_string()),
+        }
+    }
+
+    /// label for the tab and title bar
+    pub fn name(&self) -> &str {
+        if self.scratch {
+            return "Scratch";
+        }
+
+        self.path
+            .as_deref()
+            .and_then(Path::file_name)
+            .and_then(|name| name.to_str())
+            .unwrap_or("New File")
+    }
+
+    /// language picked by the user, or detected from the extension
+    pub fn language(&self) -> Language {
+        self.language.unwrap_or_else(|| Language::detect(self.path.as_deref()))
+    }
+
+    /// cursor column with tabs expanded to `tab_width`
+    pub fn visual_column(&self, tab_width: usize) -> usize {
+        let (line, column) = self.content.cursor_position();
+        let text = self.content.line(line).map(|line| line.to_string()).unwrap_or_default();
+
+        text.chars().take(column).fold(0, |visual, c| match c {
+            '\t' => visual + tab_width - visual % tab_width,
+            _ => visual + 1,
+        })
+    }
+
+    /// the cursor line's leading spaces and tabs, and its trailing whitespace, in chars
+    pub fn line_whitespace(&self) -> (usize, usize, usize) {
+        let line = self.content.cursor_position().0;
+        let text = self.content.line(line).map(|text| text.to_string()).unwrap_or_default();
+        let leading = text.chars().take_while(|c| *c == ' ' || *c == '\t');
+        let (spaces, tabs) = leading.fold((0, 0), |(spaces, tabs), c| match c {
+            ' ' => (spaces + 1, tabs),
+            _ => (spaces, tabs + 1),
+        });
+        let trailing = text.chars().rev().take_while(|c| c.is_whitespace()).count();
+
+        // an all-blank line is leading whitespace, not trailing
+        match trailing == text.chars().count() {
+            true => (spaces, tabs, 0),
+            false => (spaces, tabs, trailing),
+        }
+    }
+
+    /// an untouched new buffer, which opening a file may replace
+    pub fn is_pristine(&self) -> bool {
+        self.path.is_none() && !self.is_dirty && self.history.is_empty() && !self.scratch
+    }
+
+    /// write the buffer to its path, asking for one if needed
+    pub fn save(&self, backup_suffix: Option<String>) -> impl Future<Output = Result<PathBuf, Error>> {
+        save_file(self.path.clone(), self.content.text(), self.line_ending, self.encoding, backup_suffix)
+    }
+
+    /// write the buffer to a newly chosen path
+    pub fn save_as(&self, backup_suffix: Option<String>) -> impl Future<Output = Result<PathBuf, Error>> {
+        save_file(None, self.content.text(), self.line_ending, self.encoding, backup_suffix)
+    }
+
+    /// apply an action to the content and record it in the history
+    ///
+    /// mouse positions depend on the layout, so clicks and drags are stored
+    /// as the equivalent cursor motions to keep the history replayable
+    pub fn perform(&mut self, action: Action) {
+        // moving and selecting is still fine in read-only mode
+        if self.read_only && action.is_edit() {
+            return;
+        }
+
+        // nothing can happen that would need replaying
+        if self.light {
+            self.edit_content(action);
+            return;
+        }
+
+        if action.is_edit() {
+            self.undone.clear();
+            self.is_dirty = true;
+
+            // the saved text was undone past, and is typed over now
+            if self.saved_edits.is_some_and(|saved| saved > self.edits) {
+                self.saved_edits = None;
+            }
+        }
+
+        match action {
+            Action::Click(_) => {
+                self.edit_content(action);
+                let anchor = self.content.cursor_position();
+
+                self.drag = Some(Drag { mark: self.history.len(), anchor });
+                self.history.extend(cursor_moves(anchor));
+            },
+            Action::Drag(_) => {
+                self.edit_content(action);
+
+                if let Some(drag) = &self.drag {
+                    let head = self.content.cursor_position();
+
+                    self.history.truncate(drag.mark);
+                    self.history.extend(cursor_moves(drag.anchor));
+                    self.history.extend(selection_moves(drag.anchor, head));
+                }
+            },
+            action => {
+                let is_edit = action.is_edit();
+
+                if is_edit {
+                    self.compact_moves();
+                }
+
+                self.edit_content(action.clone());
+                self.history.push(action);
+                self.drag = None;
+
+                if is_edit {
+                    self.edits += 1;
+                    self.moves_from = self.history.len();
+                    self.content_changed();
+
+                    if self.history.len() > MAX_HISTORY {
+                        self.fold_history();
+                    }
+                }
+            },
+        }
+    }
+
+    /// drop everything from the last edit on and replay the rest
+    pub fn undo(&mut self) {
+        if self.read_only {
+            return;
+        }
+
+        if let Some(last_edit) = self.history.iter().rposition(Action::is_edit) {
+            self.undone.push(self.history.split_off(last_edit));
+            self.content = text_editor::Content::with(&self.base);
+            self.anchor = None;
+            self.drag = None;
+
+            let history = std::mem::take(&mut self.history);
+            for action in &history {
+                self.edit_content(action.clone());
+            }
+            self.history = history;
+            self.edits -= 1;
+            self.moves_from = self.history.iter().rposition(Action::is_edit).map_or(0, |edit| edit + 1);
+            self.is_dirty = self.saved_edits != Some(self.edits);
+            self.content_changed();
+        }
+    }
+
+    /// replay the last undone edit
+    pub fn redo(&mut self) {
+        if self.read_only {
+            return;
+        }
+
+        if let Some(actions) = self.undone.pop() {
+            // an undone group is its edit and the motions after it
+            self.moves_from = self.history.len() + 1;
+            self.drag = None;
+            for action in actions {
+                self.edit_content(action.clone());
+                self.history.push(action);
+            }
+            self.edits += 1;
+            self.drag = None;
+            self.is_dirty = self.saved_edits != Some(self.edits);
+            self.content_changed();
+        }
+    }
+
+    /// hand `action` to the editor, following where its selection starts
+    fn edit_content(&mut self, action: Action) {
+        let cursor = self.content.cursor_position();
+
+        self.anchor = match action {
+            // a new selection starts at the cursor, extending one keeps its start
+            Action::Select(_) => self.anchor.or_else(|| {
+                let other = self.guessed_range().map(|(start, end)| if start == cursor { end } else { start });
+                Some(other.unwrap_or(cursor))
+            }),
+            Action::Drag(_) => self.drag.as_ref().map(|drag| drag.anchor).or(self.anchor).or(Some(cursor)),
+            Action::Scroll { .. } => self.anchor,
+            // the editor picks both ends of a word or line itself
+            Action::SelectWord | Action::SelectLine => None,
+            Action::Move(_) | Action::Click(_) | Action::Edit(_) => None,
+        };
+
+        self.content.edit(action);
+    }
+
+    /// the text matches the file now, undoing or redoing back here makes it clean again
+    pub fn mark_saved(&mut self) {
+        self.is_dirty = false;
+        self.saved_edits = Some(self.edits);
+    }
+
+    /// swap the motions since the last edit for the direct ones to the cursor,
+    /// when that's shorter, so undo has less to replay
+    fn compact_moves(&mut self) {
+        let moves = self.history.len() - self.moves_from;
+        let (line, column) = self.content.cursor_position();
+
+        // a selection the editor made itself can't be replayed from its anchor
+        if moves <= line + column + 2 || self.content.selection().is_some() {
+            return;
+        }
+
+        self.history.truncate(self.moves_from);
+        self.history.extend(cursor_moves((line, column)));
+    }
+
+    /// replay the older half of the history onto the base, which gives up
+    /// undoing that far back but keeps each undo bounded
+    fn fold_history(&mut self) {
+        // at an edit, so the groups undo takes apart stay whole
+        let half = self.history.len() / 2;
+        let Some(split) = self.history[half..].iter().position(Action::is_edit).map(|index| half + index) else {
+            return;
+        };
+
+        let mut content = text_editor::Content::with(&self.base);
+        for action in &self.history[..split] {
+            content.edit(action.clone());
+        }
+
+        let folded = self.history[..split].iter().filter(|action| action.is_edit()).count();
+        self.base = Arc::new(content.text());
+        self.history.drain(..split);
+        self.edits -= folded;
+        self.moves_from -= split;
+        self.saved_edits = self.saved_edits.and_then(|saved| saved.checked_sub(folded));
+    }
+
+    /// place the cursor at `position` through recorded cursor motions
+    pub fn move_to(&mut self, position: (usize, usize)) {
+        for action in cursor_moves(position) {
+            self.perform(action);
+        }
+    }
+
+    /// like `move_to`, but pulled back inside the text if it's past the end
+    pub fn move_to_clamped(&mut self, (line, column): (usize, usize)) {
+        let line = line.min(self.content.line_count().saturating_sub(1));
+        self.move_to((line, column.min(self.line_length(line))));
+    }
+
+    /// select from `start` to `end` through recorded cursor motions
+    pub fn select(&mut self, start: (usize, usize), end: (usize, usize)) {
+        for action in cursor_moves(start).into_iter().chain(selection_moves(start, end)) {
+            self.perform(action);
+        }
+    }
+
+    /// swap the whole text in a single undoable edit
+    pub fn replace_text(&mut self, text: String) {
+        self.perform(Action::Move(Motion::DocumentStart));
+        self.perform(Action::Select(Motion::DocumentEnd));
+        self.perform(Action::Edit(Edit::Paste(Arc::new(text))));
+    }
+
+    /// paste `text` over the selection, or at the cursor; where the pasted text starts and ends
+    pub fn paste(&mut self, text: String) -> ((usize, usize), (usize, usize)) {
+        let start = match self.selection_range() {
+            Some((start, _)) => start,
+            None => self.content.cursor_position(),
+        };
+        self.perform(Action::Edit(Edit::Paste(Arc::new(text))));
+
+        (start, self.content.cursor_position())
+    }
+
+    /// the widget-free state, for the transitions in `editor_core`
+    pub fn document(&self) -> Document {
+        Document {
+            path: self.path.clone(),
+            text: self.content.text(),
+            is_dirty: self.is_dirty,
+            error: self.error.clone(),
+        }
+    }
+
+    /// take on the state a transition returned
+    ///
+    /// a changed text goes in as one undoable edit, so it also marks the buffer dirty
+    pub fn apply(&mut self, document: Document) {
+        let Document { path, text, is_dirty, error } = document;
+
+        // a scratch buffer never turns into the file it was saved as
+        self.path = if self.scratch { None } else { path };
+        self.error = error;
+
+        // a changed text is an edit, whatever the document says
+        match self.set_text(text) || is_dirty {
+            true => self.is_dirty = true,
+            false => self.mark_saved(),
+        }
+    }
+
+    /// rewrite the whole text in one undoable edit, leaving the cursor where it was
+    ///
+    /// `false` if the text was the same already
+    fn set_text(&mut self, text: String) -> bool {
+        if text == self.content.text() {
+            return false;
+        }
+
+        let (line, column) = self.content.cursor_position();
+        self.replace_text(text);
+
+        let line = line.min(self.content.line_count().saturating_sub(1));
+        self.move_to((line, column.min(self.line_length(line))));
+        true
+    }
+
+    /// length of `line` in chars, 0 past the end
+    pub fn line_length(&self, line: usize) -> usize {
+        self.content.line(line).map(|text| text.to_string().chars().count()).unwrap_or(0)
+    }
+
+    /// copy the cursor's line below itself, keeping the column
+    pub fn duplicate_line(&mut self) {
+        if self.read_only {
+            return;
+        }
+
+        let (line, column) = self.content.cursor_position();
+        let text = self.content.line(line).map(|text| text.to_string()).unwrap_or_default();
+
+        self.move_to((line, self.line_length(line)));
+        self.perform(Action::Edit(Edit::Paste(Arc::new(format!("\n{text}")))));
+        self.move_to((line + 1, column));
+    }
+
+    /// break the line, carrying its indentation over and adding `unit` after
+    /// an opening bracket when `code` is set
+    pub fn indented_newline(&mut self, unit: &str, code: bool) {
+        let (line, column) = self.content.cursor_position();
+        let text = self.content.line(line).map(|text| text.to_string()).unwrap_or_default();
+        let before: String = text.chars().take(column).collect();
+
+        let mut indent: String = before.chars().take_while(|c| *c == ' ' || *c == '\t').collect();
+        if code && before.trim_end().ends_with(['{', '(', '[']) {
+            indent.push_str(unit);
+        }
+
+        self.perform(Action::Edit(Edit::Paste(Arc::new(format!("\n{indent}")))));
+    }
+
+    /// type `c`, closing brackets and quotes as they open and typing over the
+    /// closing one when it's already there
+    pub fn insert_paired(&mut self, c: char) {
+        let (before, after) = self.chars_around();
+        let typed = Action::Edit(Edit::Insert(c));
+
+        if self.read_only || self.content.selection().is_some() {
+            return self.perform(typed);
+        }
+
+        if after == Some(c) && (is_closing(c) || closing(c) == Some(c)) {
+            return self.perform(Action::Move(Motion::Right));
+        }
+
+        // only pair up where a closing char wouldn't get in the way
+        let free_after = match after {
+            Some(after) => after.is_whitespace() || is_closing(after),
+            None => true,
+        };
+        let is_quote = closing(c) == Some(c);
+        let word_before = before.is_some_and(char::is_alphanumeric); // don't pair the ' in "it's"
+
+        match closing(c) {
+            Some(close) if free_after && !(is_quote && word_before) => {
+                self.perform(Action::Edit(Edit::Paste(Arc::new(format!("{c}{close}")))));
+                self.perform(Action::Move(Motion::Left));
+            },
+            _ => self.perform(typed),
+        }
+    }
+
+    /// surround the selection with `c` and what closes it, in one undoable
+    /// edit, keeping the wrapped text selected
+    pub fn wrap_selection(&mut self, c: char) {
+        let (Some(close), Some(selection), Some((start, _))) = (closing(c), self.content.selection(), self.selection_range()) else {
+            return self.perform(Action::Edit(Edit::Insert(c)));
+        };
+
+        if self.read_only {
+            return;
+        }
+
+        self.perform(Action::Edit(Edit::Paste(Arc::new(format!("{c}{selection}{close}")))));
+
+        // the cursor ends up after the closing char, on the selection's last line
+        let (line, column) = self.content.cursor_position();
+        self.select((start.0, start.1 + 1), (line, column.saturating_sub(1)));
+    }
+
+    /// backspace, taking an empty pair like `()` out whole
+    pub fn backspace_paired(&mut self) {
+        let (before, after) = self.chars_around();
+        let is_pair = before.and_then(closing).is_some_and(|close| after == Some(close));
+
+        if is_pair && !self.read_only && self.content.selection().is_none() {
+            self.perform(Action::Move(Motion::Left));
+            self.perform(Action::Select(Motion::Right));
+            self.perform(Action::Select(Motion::Right));
+        }
+
+        self.perform(Action::Edit(Edit::Backspace));
+    }
+
+    /// the chars right before and after the cursor, on its line
+    fn chars_around(&self) -> (Option<char>, Option<char>) {
+        let (line, column) = self.content.cursor_position();
+        let text = self.content.line(line).map(|text| text.to_string()).unwrap_or_default();
+        let mut chars = text.chars().skip(column.saturating_sub(1));
+
+        match column {
+            0 => (None, chars.next()),
+            _ => (chars.next(), chars.next()),
+        }
+    }
+
+    /// start and end of the selection, in document order
+    pub fn selection_range(&self) -> Option<((usize, usize), (usize, usize))> {
+        let cursor = self.content.cursor_position();
+
+        match self.anchor {
+            Some(anchor) if anchor != cursor => Some((cursor.min(anchor), cursor.max(anchor))),
+            Some(_) => None,
+            None => self.guessed_range(),
+        }
+    }
+
+    /// the selection's ends when the editor made it, and we only know the cursor
+    /// is one of them; the text before the cursor decides which, so a selection
+    /// over repeated text can be read from the wrong end
+    fn guessed_range(&self) -> Option<((usize, usize), (usize, usize))> {
+        let selection = self.content.selection()?;
+        let cursor = self.content.cursor_position();
+
+        let text = self.content.text();
+        let offset = search::offset(&text, cursor);
+        let other = match text.get(offset.saturating_sub(selection.len())..offset) {
+            Some(before) if before == selection => offset - selection.len(),
+            _ => (offset + selection.len()).min(text.len()),
+        };
+        let other = search::position(&text, other);
+
+        Some((cursor.min(other), cursor.max(other)))
+    }
+
+    /// go to the first non-blank char of the line, or to its start if the
+    /// cursor is there already; a line of only whitespace toggles between its
+    /// start and end, and an empty one has nowhere to go
+    pub fn smart_home(&mut self, select: bool) {
+        let (line, column) = self.content.cursor_position();
+        let indent = self
+            .content
+            .line(line)
+            .map(|text| text.to_string().chars().take_while(|c| c.is_whitespace()).count())
+            .unwrap_or(0);
+        let target = if column == indent { 0 } else { indent };
+
+        if select {
+            let motion = if target < column { Motion::Left } else { Motion::Right };
+            for _ in 0..column.abs_diff(target) {
+                self.perform(Action::Select(motion));
+            }
+        } else {
+            // from the start, moving left with a selection would only collapse it
+            self.perform(Action::Move(Motion::Home));
+            for _ in 0..target {
+                self.perform(Action::Move(Motion::Right));
+            }
+        }
+    }
+
+    /// move to the partner of the bracket after the cursor, or else the one
+    /// before it; `false` when there's no bracket there or it's unmatched
+    pub fn jump_to_bracket(&mut self) -> bool {
+        let text = self.content.text();
+        let offset = search::offset(&text, self.content.cursor_position());
+
+        let before = text[..offset].char_indices().next_back().map(|(index, _)| index);
+        let partner = matching_bracket(&text, offset).or_else(|| before.and_then(|before| matching_bracket(&text, before)));
+
+        match partner {
+            Some(partner) => {
+                self.move_to(search::position(&text, partner));
+                true
+            },
+            None => false,
+        }
+    }
+
+    /// select the cursor's line, or one more line if whole lines are selected
+    /// already, so pressing it again keeps growing the selection
+    pub fn select_line(&mut self) {
+        let last = self.content.line_count().saturating_sub(1);
+        let document_end = (last, self.line_length(last));
+
+        let (start, end_line) = match self.selection_range() {
+            Some((start, end)) if start.1 == 0 && end.0 > start.0 && (end.1 == 0 || end == document_end) => {
+                (start, end.0 + 1)
+            },
+            _ => {
+                let line = self.content.cursor_position().0;
+                ((line, 0), line + 1)
+            },
+        };
+
+        // the last line has no newline to end at
+        let end = match end_line > last {
+            true => document_end,
+            false => (end_line, 0),
+        };
+        self.select(start, end);
+    }
+
+    pub fn select_all(&mut self) {
+        self.perform(Action::Move(Motion::DocumentStart));
+        self.perform(Action::Select(Motion::DocumentEnd));
+    }
+
+    /// first and last line touched by the selection, or the cursor's line
+    pub fn selected_lines(&self) -> (usize, usize) {
+        match self.selection_range() {
+            // a selection of whole lines ends at the start of the next one
+            Some((start, end)) if end.1 == 0 && end.0 > start.0 => (start.0, end.0 - 1),
+            Some((start, end)) => (start.0, end.0),
+            None => {
+                let line = self.content.cursor_position().0;
+                (line, line)
+            },
+        }
+    }
+
+    /// sort the selected lines, expanded to whole lines, and select them
+    pub fn sort_lines(&mut self, ascending: bool, case_insensitive: bool, unique: bool) {
+        let (first, last) = self.selected_lines();
+
+        if self.read_only || first == last {
+            return;
+        }
+
+        let mut lines: Vec<_> = (first..=last)
+            .map(|line| self.content.line(line).map(|text| text.to_string()).unwrap_or_default())
+            .collect();
+        let original = lines.clone();
+
+        match case_insensitive {
+            true => lines.sort_by_cached_key(|line| line.to_lowercase()),
+            false => lines.sort(),
+        }
+        if !ascending {
+            lines.reverse();
+        }
+        if unique {
+            lines.dedup_by(|a, b| match case_insensitive {
+                true => a.to_lowercase() == b.to_lowercase(),
+                false => a == b,
+            });
+        }
+
+        if lines == original {
+            return;
+        }
+
+        let end = (first + lines.len() - 1, lines.last().map_or(0, |line| line.chars().count()));
+
+        // the newline after the last line stays where it is
+        self.select((first, 0), (last, self.line_length(last)));
+        self.perform(Action::Edit(Edit::Paste(Arc::new(lines.join("\n")))));
+        self.select((first, 0), end);
+    }
+
+    /// keep only the selected text, as one undoable edit; the path stays so
+    /// the snippet can be saved elsewhere
+    pub fn crop_to_selection(&mut self) {
+        if let Some(selection) = self.content.selection() {
+            self.replace_text(selection);
+        }
+    }
+
+    /// rewrite the selection, or the word at the cursor, and select the result
+    pub fn transform_selection(&mut self, f: impl FnOnce(&str) -> String) {
+        if self.read_only {
+            return;
+        }
+
+        if self.content.selection().is_none() {
+            self.perform(Action::SelectWord);
+        }
+
+        let (Some(selection), Some((start, _))) = (self.content.selection(), self.selection_range()) else {
+            return;
+        };
+
+        let transformed = f(&selection);
+        if transformed == selection {
+            return;
+        }
+
+        self.perform(Action::Edit(Edit::Paste(Arc::new(transformed))));
+        let end = self.content.cursor_position();
+        self.select(start, end);
+    }
+
+    /// comment out the selected lines, or uncomment them if they all are
+    pub fn toggle_comment(&mut self, comment: Comment) {
+        if self.read_only {
+            return;
+        }
+
+        let (first, last) = self.selected_lines();
+        let (line, column) = self.content.cursor_position();
+        let lines: Vec<_> = (first..=last)
+            .map(|line| self.content.line(line).map(|text| text.to_string()).unwrap_or_default())
+            .collect();
+        let toggled = comment.toggle(&lines);
+
+        if toggled == lines {
+            return;
+        }
+
+        // keep the cursor on the same text while the line's start shifts; a
+        // selection of whole lines leaves it on the untouched line after them
+        let shift = match line.checked_sub(first).filter(|&index| index < lines.len()) {
+            Some(index) => toggled[index].chars().count() as isize - lines[index].chars().count() as isize,
+            None => 0,
+        };
+
+        self.select((first, 0), (last, self.line_length(last)));
+        self.perform(Action::Edit(Edit::Paste(Arc::new(toggled.join("\n")))));
+        self.move_to((line, column.saturating_add_signed(shift).min(self.line_length(line))));
+    }
+
+    /// swap the cursor's line with the one above, keeping the cursor on it
+    pub fn move_line_up(&mut self) {
+        let (line, column) = self.content.cursor_position();
+
+        if self.read_only || line == 0 {
+            return;
+        }
+
+        self.swap_lines(line - 1);
+        self.move_to((line - 1, column));
+    }
+
+    /// swap the cursor's line with the one below, keeping the cursor on it
+    pub fn move_line_down(&mut self) {
+        let (line, column) = self.content.cursor_position();
+
+        if self.read_only || line + 1 >= self.content.line_count() {
+            return;
+        }
+
+        self.swap_lines(line);
+        self.move_to((line + 1, column));
+    }
+
+    /// swap `first` and the line after it in one edit
+    fn swap_lines(&mut self, first: usize) {
+        let line = |buffer: &Self, line| buffer.content.line(line).map(|text| text.to_string()).unwrap_or_default();
+        let swapped = format!("{}\n{}", line(self, first + 1), line(self, first));
+
+        self.select((first, 0), (first + 1, self.line_length(first + 1)));
+        self.perform(Action::Edit(Edit::Paste(Arc::new(swapped))));
+    }
+
+    /// remove the cursor's line, taking its newline with it
+    pub fn delete_line(&mut self) {
+        if self.read_only {
+            return;
+        }
+
+        let (line, column) = self.content.cursor_position();
+        let last = self.content.line_count().saturating_sub(1);
+
+        // the last line has no newline after it, so take the one before instead
+        let (start, end) = match line {
+            line if line < last => ((line, 0), (line + 1, 0)),
+            0 => ((0, 0), (0, self.line_length(0))),
+            line => ((line - 1, self.line_length(line - 1)), (line, self.line_length(line))),
+        };
+
+        self.select(start, end);
+        self.perform(Action::Edit(Edit::Delete));
+
+        let line = line.min(self.content.line_count().saturating_sub(1));
+        self.move_to((line, column.min(self.line_length(line))));
+    }
+
+    /// whether there are cursors besides the editor's own, and they still fit the text
+    pub fn has_extra_cursors(&self) -> bool {
+        !self.extra.is_empty() && self.extra_revision == self.revision
+    }
+
+    /// the editor's cursor and the extra ones
+    pub fn cursor_count(&self) -> usize {
+        match self.has_extra_cursors() {
+            true => self.extra.len() + 1,
+            false => 1,
+        }
+    }
+
+    pub fn clear_cursors(&mut self) {
+        self.extra.clear();
+    }
+
+    /// leave a cursor, or selection, behind where the editor's is now
+    pub fn add_cursor(&mut self) {
+        let text = self.content.text();
+        let range = self.selection_offsets(&text);
+
+        if !self.has_extra_cursors() {
+            self.extra.clear();
+        }
+        if !self.extra.contains(&range) {
+            self.extra.push(range);
+        }
+        self.extra_revision = self.revision;
+    }
+
+    /// select the next occurrence of the selection, keeping a cursor on the
+    /// old one; without a selection this selects the word at the cursor first
+    pub fn add_next_occurrence(&mut self) {
+        let Some(needle) = self.content.selection() else {
+            self.perform(Action::SelectWord);
+            return;
+        };
+
+        let text = self.content.text();
+        let current = self.selection_offsets(&text);
+        if !self.has_extra_cursors() {
+            self.extra.clear();
+        }
+
+        // search on from the furthest one, wrapping around to the top
+        let taken: Vec<_> = self.extra.iter().copied().chain([current]).collect();
+        let after = taken.iter().map(|&(_, end)| end).max().unwrap_or(0);
+        let found = text[after..]
+            .match_indices(&needle)
+            .map(|(start, _)| after + start)
+            .chain(text[..after].match_indices(&needle).map(|(start, _)| start))
+            .map(|start| (start, start + needle.len()))
+            .find(|range| !taken.contains(range));
+
+        let Some((start, end)) = found else {
+            return;
+        };
+
+        self.extra.push(current);
+        self.extra_revision = self.revision;
+        self.select(search::position(&text, start), search::position(&text, end));
+    }
+
+    /// start a rectangular selection where the cursor is, for a drag to grow
+    pub fn start_block(&mut self) {
+        let cursor = self.content.cursor_position();
+        self.block = Some((cursor, cursor));
+    }
+
+    /// stretch the rectangle started by `start_block` to the cursor
+    pub fn drag_block(&mut self) {
+        if let Some((anchor, _)) = self.block {
+            self.select_block(anchor, self.content.cursor_position());
+        }
+    }
+
+    /// grow the rectangle a line up or down, starting from the cursor, or the
+    /// selection when it's within one line
+    pub fn extend_block(&mut self, down: bool) {
+        let cursor = self.content.cursor_position();
+        let (anchor, head) = match (self.block, self.selection_range()) {
+            (Some(block), _) if self.has_extra_cursors() => block,
+            (_, Some((start, end))) if start.0 == end.0 => (if start == cursor { end } else { start }, cursor),
+            _ => (cursor, cursor),
+        };
+        let line = match down {
+            true => (head.0 + 1).min(self.content.line_count().saturating_sub(1)),
+            false => head.0.saturating_sub(1),
+        };
+
+        self.select_block(anchor, (line, head.1));
+    }
+
+    /// the editor's selection on the head's line and an extra one on each of
+    /// the others, cut short by shorter lines; lines ending left of it are skipped
+    fn select_block(&mut self, anchor: (usize, usize), head: (usize, usize)) {
+        let text = self.content.text();
+        let (left, right) = (anchor.1.min(head.1), anchor.1.max(head.1));
+
+        self.extra = (anchor.0.min(head.0)..=anchor.0.max(head.0))
+            .filter(|&line| line != head.0)
+            .map(|line| (line, self.line_length(line)))
+            .filter(|&(_, length)| left == 0 || length >= left)
+            .map(|(line, length)| {
+                (search::offset(&text, (line, left.min(length))), search::offset(&text, (line, right.min(length))))
+            })
+            .collect();
+        self.extra_revision = self.revision;
+
+        let length = self.line_length(head.0);
+        self.select((head.0, anchor.1.min(length)), (head.0, head.1.min(length)));
+        self.block = Some((anchor, head));
+    }
+
+    /// make `edit` at every cursor, as one undoable edit
+    pub fn edit_all(&mut self, edit: Edit) {
+        if self.read_only {
+            return;
+        }
+
+        let text = self.content.text();
+        let primary = self.selection_offsets(&text);
+        let mut ranges: Vec<_> = self.extra.iter().copied().chain([primary]).collect();
+        ranges.sort();
+        ranges.dedup();
+
+        let inserted = match &edit {
+            Edit::Insert(c) => c.to_string(),
+            Edit::Paste(pasted) => pasted.as_ref().clone(),
+            Edit::Enter => String::from("\n"),
+            Edit::Backspace | Edit::Delete => String::new(),
+        };
+
+        // rebuild the text front to back, so every new cursor offset is known as it goes
+        let mut result = String::with_capacity(text.len());
+        let mut cursors = Vec::with_capacity(ranges.len());
+        let mut copied = 0;
+
+        for &(start, end) in &ranges {
+            // a bare cursor takes the char next to it instead
+            let (start, end) = match edit {
+                Edit::Backspace if start == end => {
+                    (text[..start].char_indices().next_back().map_or(start, |(index, _)| index), end)
+                },
+                Edit::Delete if start == end => (start, text[end..].chars().next().map_or(end, |c| end + c.len_utf8())),
+                _ => (start, end),
+            };
+            // cursors that ran into each other
+            let start = start.max(copied);
+            let end = end.max(start);
+
+            result.push_str(&text[copied..start]);
+            result.push_str(&inserted);
+            cursors.push(result.len());
+            copied = end;
+        }
+        result.push_str(&text[copied..]);
+
+        let index = ranges.iter().position(|&range| range == primary).unwrap_or(0);
+        let position = search::position(&result, cursors[index]);
+
+        self.replace_text(result);
+        self.move_to(position);
+
+        self.extra = cursors
+            .into_iter()
+            .enumerate()
+            .filter(|&(other, _)| other != index)
+            .map(|(_, offset)| (offset, offset))
+            .collect();
+        self.extra_revision = self.revision;
+    }
+
+    /// line and byte range within it of each extra cursor, for the highlighter;
+    /// a bare cursor shows as the char after it
+    pub fn extra_highlights(&self) -> Vec<(usize, Range<usize>)> {
+        if !self.has_extra_cursors() {
+            return Vec::new();
+        }
+
+        let text = self.content.text();
+        let mut highlights = Vec::new();
+
+        for &(start, end) in &self.extra {
+            let end = match start == end {
+                true => text[start..].chars().next().filter(|&c| c != '\n').map_or(start, |c| start + c.len_utf8()),
+                false => end,
+            };
+
+            // the highlighter goes line by line, so split at the newlines
+            let (mut line, _) = search::position(&text, start);
+            let mut line_start = text[..start].rfind('\n').map_or(0, |newline| newline + 1);
+            let mut from = start;
+
+            while from < end {
+                let line_end = text[from..].find('\n').map_or(text.len(), |newline| from + newline);
+                let to = end.min(line_end);
+
+                if to > from {
+                    highlights.push((line, from - line_start..to - line_start));
+                }
+
+                line += 1;
+                line_start = line_end + 1;
+                from = line_start;
+            }
+        }
+
+        highlights
+    }
+
+    /// byte range of the selection, or the cursor as an empty one
+    fn selection_offsets(&self, text: &str) -> (usize, usize) {
+        match self.selection_range() {
+            Some((start, end)) => (search::offset(text, start), search::offset(text, end)),
+            None => {
+                let offset = search::offset(text, self.content.cursor_position());
+                (offset, offset)
+            },
+        }
+    }
+
+    /// changes whenever the text does
+    pub fn revision(&self) -> usize {
+        self.revision
+    }
+
+    /// whether the stats are waiting on `refresh_stats`
+    pub fn is_stale(&self) -> bool {
+        self.stale.is_some()
+    }
+
+    /// recount the stats once typing pauses, or when it has gone on too long
+    pub fn refresh_stats(&mut self, now: Instant) {
+        let Some(Stale { since, last }) = self.stale else {
+            return;
+        };
+
+        if now.duration_since(last) >= STATS_PAUSE || now.duration_since(since) >= STATS_MAX_DELAY {
+            self.stats = Stats::of(&self.content.text());
+            self.stale = None;
+        }
+    }
+
+    /// note the text changed, counting the whole of it on every key would stutter on big files
+    fn content_changed(&mut self) {
+        let now = Instant::now();
+        let since = self.stale.map_or(now, |stale| stale.since);
+
+        self.stale = Some(Stale { since, last: now });
+        self.revision += 1;
+    }
+}
+
+/// the char that closes `c`, for brackets and quotes
+fn closing(c: char) -> Option<char> {
+    match c {
+        '(' => Some(')'),
+        '[' => Some(']'),
+        '{' => Some('}'),
+        '"' | '\'' | '`' => Some(c),
+        _ => None,
+    }
+}
+
+/// byte offset of the bracket matching the one at `offset`, counting nesting
+/// but not telling code from strings or comments
+fn matching_bracket(text: &str, offset: usize) -> Option<usize> {
+    let bracket = text[offset..].chars().next()?;
+    let (open, close, forward) = match bracket {
+        '(' | '[' | '{' => (bracket, closing(bracket)?, true),
+        ')' => ('(', ')', false),
+        ']' => ('[', ']', false),
+        '}' => ('{', '}', false),
+        _ => return None,
+    };
+
+    // the bracket itself opens the count, so the depth never goes below zero
+    let mut depth = 0usize;
+    if forward {
+        for (index, c) in text[offset..].char_indices() {
+            if c == open {
+                depth += 1;
+            } else if c == close {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(offset + index);
+                }
+            }
+        }
+    } else {
+        for (index, c) in text[..=offset].char_indices().rev() {
+            if c == close {
+                depth += 1;
+            } else if c == open {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(index);
+                }
+            }
+        }
+    }
+
+    None
+}
+
+fn is_closing(c: char) -> bool {
+    matches!(c, ')' | ']' | '}')
+}
+
+/// motions that move the cursor from anywhere to `(line, column)`
+pub fn cursor_moves((line, column): (usize, usize)) -> Vec<Action> {
+    let mut actions = vec![Action::Move(Motion::DocumentStart)];
+    actions.extend(std::iter::repeat(Action::Move(Motion::Down)).take(line));
+    actions.push(Action::Move(Motion::Home));
+    actions.extend(std::iter::repeat(Action::Move(Motion::Right)).take(column));
+    actions
+}
+
+/// motions that extend a selection from `from` to `to`
+pub fn selection_moves(from: (usize, usize), to: (usize, usize)) -> Vec<Action> {
+    if from.0 == to.0 {
+        let motion = if to.1 < from.1 { Motion::Left } else { Motion::Right };
+        return std::iter::repeat(Action::Select(motion))
+            .take(from.1.abs_diff(to.1))
+            .collect();
+    }
+
+    let motion = if to.0 < from.0 { Motion::Up } else { Motion::Down };
+    let mut actions: Vec<_> = std::iter::repeat(Action::Select(motion))
+        .take(from.0.abs_diff(to.0))
+        .collect();
+    actions.push(Action::Select(Motion::Home));
+    actions.extend(std::iter::repeat(Action::Select(Motion::Right)).take(to.1));
+    actions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// a buffer holding `text` with the cursor at the start
+    fn buffer(text: &str) -> Buffer {
+        Buffer::from_text(0, Arc::new(text.to_owned()), LineEnding::default(), Encoding::default())
+    }
+
+    #[test]
+    fn matching_bracket_nested() {
+        let text = "(a[b]{c})";
+
+        assert_eq!(matching_bracket(text, 0), Some(8));
+        assert_eq!(matching_bracket(text, 8), Some(0));
+        assert_eq!(matching_bracket(text, 2), Some(4));
+        assert_eq!(matching_bracket(text, 7), Some(5));
+    }
+
+    #[test]
+    fn matching_bracket_unbalanced() {
+        assert_eq!(matching_bracket("(()", 0), None);
+        assert_eq!(matching_bracket("())", 2), None);
+    }
+
+    #[test]
+    fn matching_bracket_not_on_a_bracket() {
+        assert_eq!(matching_bracket("(a[b]{c})", 1), None);
+        assert_eq!(matching_bracket("(a)", 3), None);
+    }
+
+    #[test]
+    fn jump_to_bracket_after_one() {
+        let mut buffer = buffer("x (a) y");
+        buffer.move_to((0, 5));
+
+        assert!(buffer.jump_to_bracket());
+        assert_eq!(buffer.content.cursor_position(), (0, 2));
+    }
+
+    #[test]
+    fn jump_to_bracket_nowhere_near_one() {
+        let mut buffer = buffer("x (a) y");
+        buffer.move_to((0, 7));
+
+        assert!(!buffer.jump_to_bracket());
+        assert_eq!(buffer.content.cursor_position(), (0, 7));
+    }
+
+    #[test]
+    fn move_line_down_swaps_with_the_next() {
+        let mut buffer = buffer("a\nb\nc");
+        buffer.move_line_down();
+
+        assert_eq!(buffer.content.text(), "b\na\nc");
+        assert_eq!(buffer.content.cursor_position(), (1, 0));
+    }
+
+    #[test]
+    fn move_line_up_swaps_with_the_previous() {
+        let mut buffer = buffer("a\nb\nc");
+        buffer.move_to((2, 1));
+        buffer.move_line_up();
+
+        assert_eq!(buffer.content.text(), "a\nc\nb");
+        assert_eq!(buffer.content.cursor_position(), (1, 1));
+    }
+
+    #[test]
+    fn move_line_stops_at_the_ends() {
+        let mut buffer = buffer("a\nb\nc");
+        buffer.move_line_up();
+        assert_eq!(buffer.content.text(), "a\nb\nc");
+        assert_eq!(buffer.content.cursor_position(), (0, 0));
+
+        buffer.move_to((2, 0));
+        buffer.move_line_down();
+        assert_eq!(buffer.content.text(), "a\nb\nc");
+        assert_eq!(buffer.content.cursor_position(), (2, 0));
+        assert!(!buffer.is_dirty);
+    }
+
+    #[test]
+    fn select_line_grows_line_by_line() {
+        let mut buffer = buffer("a\nb\nc");
+
+        buffer.select_line();
+        assert_eq!(buffer.selection_range(), Some(((0, 0), (1, 0))));
+
+        buffer.select_line();
+        assert_eq!(buffer.selection_range(), Some(((0, 0), (2, 0))));
+
+        // the last line has no newline, so the selection ends with the text
+        buffer.select_line();
+        assert_eq!(buffer.selection_range(), Some(((0, 0), (2, 1))));
+
+        buffer.select_line();
+        assert_eq!(buffer.selection_range(), Some(((0, 0), (2, 1))));
+    }
+
+    #[test]
+    fn select_line_on_the_last_line() {
+        let mut buffer = buffer("a\nb\nc");
+        buffer.move_to((2, 0));
+
+        buffer.select_line();
+        assert_eq!(buffer.selection_range(), Some(((2, 0), (2, 1))));
+
+        buffer.select_line();
+        assert_eq!(buffer.selection_range(), Some(((2, 0), (2, 1))));
+    }
+
+    #[test]
+    fn select_all_spans_the_text() {
+        let mut buffer = buffer("a\nb\nc");
+        buffer.move_to((1, 1));
+        buffer.select_all();
+
+        assert_eq!(buffer.selection_range(), Some(((0, 0), (2, 1))));
+    }
+
+    #[test]
+    fn toggle_comment_after_select_line() {
+        let mut buffer = buffer("a\nb\nc");
+        buffer.select_line();
+        buffer.toggle_comment(Comment::Line("//"));
+
+        // the line the selection ended on is left alone, cursor and all
+        assert_eq!(buffer.content.text(), "// a\nb\nc");
+        assert_eq!(buffer.content.cursor_position(), (1, 0));
+    }
+
+    #[test]
+    fn toggle_comment_after_shift_down() {
+        let mut buffer = buffer("a\nb\nc");
+        buffer.move_to((1, 0));
+        buffer.perform(Action::Select(Motion::Down));
+        buffer.toggle_comment(Comment::Line("#"));
+
+        assert_eq!(buffer.content.text(), "a\n# b\nc");
+        assert_eq!(buffer.content.cursor_position(), (2, 0));
+    }
+
+    #[test]
+    fn toggle_comment_keeps_the_cursor_on_its_text() {
+        let mut buffer = buffer("fn a() {}");
+        buffer.move_to((0, 3));
+
+        buffer.toggle_comment(Comment::Line("//"));
+        assert_eq!(buffer.content.text(), "// fn a() {}");
+        assert_eq!(buffer.content.cursor_position(), (0, 6));
+
+        buffer.toggle_comment(Comment::Line("//"));
+        assert_eq!(buffer.content.text(), "fn a() {}");
+        assert_eq!(buffer.content.cursor_position(), (0, 3));
+    }
+
+    #[test]
+    fn toggle_comment_uncomments_when_all_lines_are() {
+        let mut buffer = buffer("// a\n//b\n\n// c");
+        buffer.select_all();
+        buffer.toggle_comment(Comment::Line("//"));
+
+        assert_eq!(buffer.content.text(), "a\nb\n\nc");
+        assert_eq!(buffer.content.cursor_position(), (3, 1));
+    }
+
+    #[test]
+    fn toggle_comment_comments_out_a_partly_commented_selection() {
+        let mut buffer = buffer("// a\nb\nc");
+        buffer.select((0, 0), (1, 1));
+        buffer.toggle_comment(Comment::Line("//"));
+
+        assert_eq!(buffer.content.text(), "// // a\n// b\nc");
+        assert_eq!(buffer.content.cursor_position(), (1, 4));
+    }
+
+    #[test]
+    fn toggle_comment_goes_after_each_lines_indentation() {
+        let mut buffer = buffer("    a\n\tb\n\n  c");
+        buffer.select_all();
+        buffer.toggle_comment(Comment::Line("#"));
+
+        // blank lines are left alone
+        assert_eq!(buffer.content.text(), "    # a\n\t# b\n\n  # c");
+
+        buffer.select_all();
+        buffer.toggle_comment(Comment::Line("#"));
+        assert_eq!(buffer.content.text(), "    a\n\tb\n\n  c");
+    }
+
+    #[test]
+    fn toggle_block_comment() {
+        let mut buffer = buffer("a\nb");
+        buffer.select_all();
+        buffer.toggle_comment(Comment::Block("<!--", "-->"));
+
+        assert_eq!(buffer.content.text(), "<!-- a -->\n<!-- b -->");
+        assert_eq!(buffer.content.cursor_position(), (1, 10));
+    }
+
+    #[test]
+    fn undoing_back_to_the_saved_text_is_clean() {
+        let mut buffer = buffer("ab");
+        buffer.perform(Action::Edit(Edit::Insert('x')));
+        assert!(buffer.is_dirty);
+
+        buffer.undo();
+        assert_eq!(buffer.content.text(), "ab");
+        assert!(!buffer.is_dirty);
+
+        buffer.redo();
+        assert_eq!(buffer.content.text(), "xab");
+        assert!(buffer.is_dirty);
+    }
+
+    #[test]
+    fn redoing_up_to_a_save_is_clean() {
+        let mut buffer = buffer("ab");
+        buffer.perform(Action::Edit(Edit::Insert('x')));
+        buffer.mark_saved();
+
+        buffer.undo();
+        assert!(buffer.is_dirty);
+
+        buffer.redo();
+        assert!(!buffer.is_dirty);
+    }
+
+    #[test]
+    fn typing_over_an_undone_save_stays_dirty() {
+        let mut buffer = buffer("ab");
+        buffer.perform(Action::Edit(Edit::Insert('x')));
+        buffer.mark_saved();
+
+        buffer.undo();
+        buffer.perform(Action::Edit(Edit::Insert('y')));
+        buffer.undo();
+
+        // back at the file's old text, not the saved one
+        assert_eq!(buffer.content.text(), "ab");
+        assert!(buffer.is_dirty);
+    }
+
+    #[test]
+    fn wandering_cursor_is_compacted_before_an_edit() {
+        let mut buffer = buffer("abc\ndef");
+        for _ in 0..20 {
+            buffer.perform(Action::Move(Motion::Right));
+            buffer.perform(Action::Move(Motion::Left));
+        }
+        buffer.perform(Action::Move(Motion::Right));
+        buffer.perform(Action::Edit(Edit::Insert('x')));
+
+        assert_eq!(buffer.content.text(), "axbc\ndef");
+        assert!(buffer.history.len() < 10);
+
+        // replaying the shorter history lands in the same place
+        buffer.perform(Action::Edit(Edit::Insert('y')));
+        buffer.undo();
+        assert_eq!(buffer.content.text(), "axbc\ndef");
+        assert_eq!(buffer.content.cursor_position(), (0, 2));
+    }
+
+    #[test]
+    fn long_histories_are_folded_into_the_base() {
+        let mut buffer = buffer("");
+        for _ in 0..MAX_HISTORY + 1 {
+            buffer.perform(Action::Edit(Edit::Insert('x')));
+        }
+
+        assert!(buffer.history.len() <= MAX_HISTORY);
+        assert_eq!(buffer.content.text().len(), MAX_HISTORY + 1);
+
+        buffer.undo();
+        assert_eq!(buffer.content.text().len(), MAX_HISTORY);
+        assert!(buffer.is_dirty);
+    }
+
+    #[test]
+    fn backward_selection_over_repeated_text() {
+        let mut buffer = buffer("abab");
+        buffer.select((0, 4), (0, 2));
+
+        assert_eq!(buffer.content.selection().as_deref(), Some("ab"));
+        assert_eq!(buffer.selection_range(), Some(((0, 2), (0, 4))));
+
+        buffer.transform_selection(|text| text.to_uppercase());
+        assert_eq!(buffer.content.text(), "abAB");
+        assert_eq!(buffer.selection_range(), Some(((0, 2), (0, 4))));
+    }
+
+    #[test]
+    fn selection_anchor_survives_undo() {
+        let mut buffer = buffer("abab");
+        buffer.perform(Action::Edit(Edit::Insert('x')));
+        buffer.perform(Action::Edit(Edit::Insert('y')));
+        buffer.select((0, 6), (0, 4));
+        buffer.undo();
+
+        // back to before the last edit, with no selection made yet
+        assert_eq!(buffer.content.text(), "xabab");
+        assert_eq!(buffer.selection_range(), None);
+
+        buffer.select((0, 5), (0, 3));
+        assert_eq!(buffer.selection_range(), Some(((0, 3), (0, 5))));
+    }
+
+    #[test]
+    fn next_occurrence_right_after_the_selection() {
+        let mut buffer = buffer("abab");
+        buffer.select((0, 0), (0, 2));
+        buffer.add_next_occurrence();
+
+        assert_eq!(buffer.selection_range(), Some(((0, 2), (0, 4))));
+        assert_eq!(buffer.extra, vec![(0, 2)]);
+
+        // both are taken, so there's nothing left to add
+        buffer.add_next_occurrence();
+        assert_eq!(buffer.cursor_count(), 2);
+    }
+
+    #[test]
+    fn next_occurrence_from_a_backward_selection() {
+        let mut buffer = buffer("abab");
+        buffer.select((0, 4), (0, 2));
+        buffer.add_next_occurrence();
+
+        // wraps around to the first one, keeping a cursor on the second
+        assert_eq!(buffer.selection_range(), Some(((0, 0), (0, 2))));
+        assert_eq!(buffer.extra, vec![(2, 4)]);
+    }
+
+    #[test]
+    fn edit_all_inserts_and_deletes_at_every_cursor() {
+        let mut buffer = buffer("ab\nab\nab");
+        buffer.add_cursor();
+        buffer.move_to((1, 0));
+        buffer.add_cursor();
+        buffer.move_to((2, 0));
+
+        buffer.edit_all(Edit::Insert('x'));
+        assert_eq!(buffer.content.text(), "xab\nxab\nxab");
+        assert_eq!(buffer.content.cursor_position(), (2, 1));
+        assert_eq!(buffer.cursor_count(), 3);
+
+        buffer.edit_all(Edit::Backspace);
+        assert_eq!(buffer.content.text(), "ab\nab\nab");
+        assert_eq!(buffer.cursor_count(), 3);
+
+        // a single undo takes back the whole edit
+        buffer.undo();
+        assert_eq!(buffer.content.text(), "xab\nxab\nxab");
+    }
+
+    #[test]
+    fn edit_all_replaces_every_selection() {
+        let mut buffer = buffer("ab ab ab");
+        buffer.select((0, 0), (0, 2));
+        buffer.add_next_occurrence();
+        buffer.add_next_occurrence();
+
+        buffer.edit_all(Edit::Insert('c'));
+        assert_eq!(buffer.content.text(), "c c c");
+        assert_eq!(buffer.content.cursor_position(), (0, 5));
+
+        buffer.edit_all(Edit::Delete);
+        assert_eq!(buffer.content.text(), "ccc");
+        assert_eq!(buffer.cursor_count(), 3);
+    }
+
+    #[test]
+    fn extend_block_skips_short_lines() {
+        let mut buffer = buffer("abcd\na\nabcd");
+        buffer.move_to((0, 3));
+        buffer.extend_block(true);
+
+        // the short line is cut short, the editor's cursor with it
+        assert_eq!(buffer.content.cursor_position(), (1, 1));
+        assert_eq!(buffer.extra, vec![(3, 3)]);
+
+        // once past it, the column it started at comes back and the short line is left out
+        buffer.extend_block(true);
+        assert_eq!(buffer.content.cursor_position(), (2, 3));
+        assert_eq!(buffer.extra, vec![(3, 3)]);
+    }
+
+    #[test]
+    fn extend_block_stops_at_the_ends() {
+        let mut buffer = buffer("ab\nab");
+        buffer.move_to((1, 1));
+        buffer.extend_block(true);
+
+        assert_eq!(buffer.content.cursor_position(), (1, 1));
+        assert_eq!(buffer.cursor_count(), 1);
+
+        buffer.move_to((0, 1));
+        buffer.extend_block(false);
+        assert_eq!(buffer.content.cursor_position(), (0, 1));
+        assert_eq!(buffer.cursor_count(), 1);
+    }
+
+    #[test]
+    fn extend_block_from_a_backward_selection() {
+        let mut buffer = buffer("abab\nabab");
+        buffer.select((0, 4), (0, 2));
+        buffer.extend_block(true);
+
+        assert_eq!(buffer.extra, vec![(2, 4)]);
+        assert_eq!(buffer.selection_range(), Some(((1, 2), (1, 4))));
+        assert_eq!(buffer.content.cursor_position(), (1, 2));
+    }
+
+    #[test]
+    fn wrap_a_backward_selection() {
+        let mut buffer = buffer("abab");
+        buffer.select((0, 4), (0, 2));
+        buffer.wrap_selection('(');
+
+        assert_eq!(buffer.content.text(), "ab(ab)");
+        assert_eq!(buffer.selection_range(), Some(((0, 3), (0, 5))));
+        assert_eq!(buffer.content.selection().as_deref(), Some("ab"));
+    }
+
+    #[test]
+    fn paste_over_a_backward_selection() {
+        let mut buffer = buffer("abab");
+        buffer.select((0, 4), (0, 2));
+
+        assert_eq!(buffer.paste(String::from("xyz")), ((0, 2), (0, 5)));
+        assert_eq!(buffer.content.text(), "abxyz");
+
+        // and again over what it just pasted, as pasting from the history does
+        buffer.select((0, 5), (0, 2));
+        assert_eq!(buffer.paste(String::from("c")), ((0, 2), (0, 3)));
+        assert_eq!(buffer.content.text(), "abc");
+    }
+}
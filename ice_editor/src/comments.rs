@@ -0,0 +1,90 @@
+use std::path::Path;
+
+use crate::highlight::Language;
+
+/// how a language comments out a line
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Comment {
+    Line(&'static str),
+    Block(&'static str, &'static str), // wrapped around each line
+}
+
+impl Comment {
+    /// the comment style of `language`, falling back to the extension for
+    /// files we don't highlight
+    pub fn of(language: Language, path: Option<&Path>) -> Option<Self> {
+        match language {
+            Language::Rust | Language::JavaScript | Language::C | Language::Cpp | Language::Go => Some(Comment::Line("//")),
+            Language::Python | Language::Shell | Language::Yaml => Some(Comment::Line("#")),
+            Language::Html | Language::Markdown => Some(Comment::Block("<!--", "-->")),
+            Language::Css => Some(Comment::Block("/*", "*/")),
+            Language::Json => None, // JSON has no comments
+            Language::PlainText => {
+                let extension = path
+                    .and_then(Path::extension)
+                    .and_then(|extension| extension.to_str())
+                    .map(str::to_lowercase)?;
+
+                match extension.as_str() {
+                    "toml" | "conf" | "cfg" | "ini" | "rb" | "pl" | "r" => Some(Comment::Line("#")),
+                    "lua" | "sql" | "hs" => Some(Comment::Line("--")),
+                    "java" | "kt" | "swift" | "ts" | "tsx" | "jsx" | "cs" | "scala" | "zig" => Some(Comment::Line("//")),
+                    "xml" | "svg" => Some(Comment::Block("<!--", "-->")),
+                    _ => None,
+                }
+            },
+        }
+    }
+
+    /// comment out every line, or uncomment them all if they already are
+    ///
+    /// blank lines are left alone and don't count towards the decision
+    pub fn toggle(self, lines: &[String]) -> Vec<String> {
+        let mut filled = lines.iter().filter(|line| !line.trim().is_empty()).peekable();
+
+        // nothing but blank lines, nothing to do
+        if filled.peek().is_none() {
+            return lines.to_vec();
+        }
+
+        let commented = filled.all(|line| self.strip(line).is_some());
+
+        lines
+            .iter()
+            .map(|line| match (line.trim().is_empty(), commented) {
+                (true, _) => line.clone(),
+                (false, true) => self.strip(line).unwrap_or_else(|| line.clone()),
+                (false, false) => self.wrap(line),
+            })
+            .collect()
+    }
+
+    /// the line commented out, after its indentation
+    fn wrap(self, line: &str) -> String {
+        let (indent, rest) = line.split_at(line.len() - line.trim_start().len());
+
+        match self {
+            Comment::Line(token) => format!("{indent}{token} {rest}"),
+            Comment::Block(open, close) => format!("{indent}{open} {rest} {close}"),
+        }
+    }
+
+    /// the exact inverse of `wrap`, or `None` if the line isn't commented
+    fn strip(self, line: &str) -> Option<String> {
+        let (indent, rest) = line.split_at(line.len() - line.trim_start().len());
+
+        let inner = match self {
+            Comment::Line(token) => {
+                let rest = rest.strip_prefix(token)?;
+                rest.strip_prefix(' ').unwrap_or(rest)
+            },
+            Comment::Block(open, close) => {
+                let rest = rest.strip_prefix(open)?.strip_suffix(close)?;
+                let rest = rest.strip_prefix(' ').unwrap_or(rest);
+                rest.strip_suffix(' ').unwrap_or(rest)
+            },
+        };
+
+        Some(format!("{indent}{inner}"))
+    }
+}
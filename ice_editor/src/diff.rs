@@ -0,0 +1,77 @@
+use iced::{
+    color, theme,
+    widget::{button, column, horizontal_space, row, scrollable, text},
+    Alignment, Element, Font, Length,
+};
+use similar::{ChangeTag, TextDiff};
+
+use crate::Message;
+
+/// unchanged lines kept around each change
+const CONTEXT: usize = 3;
+
+/// a unified diff of the buffer against its file on disk
+#[derive(Debug, Clone)]
+pub struct Diff {
+    pub name: String,
+    lines: Vec<Line>,
+}
+
+/// a line of the diff
+#[derive(Debug, Clone)]
+enum Line {
+    Hunk(String), // the `@@ -1,4 +1,5 @@` header
+    Same(String),
+    Removed(String),
+    Added(String),
+}
+
+impl Diff {
+    /// what it takes to turn `old` into `new`, in hunks with some context
+    pub fn new(name: String, old: &str, new: &str) -> Self {
+        let diff = TextDiff::from_lines(old, new);
+        let mut lines = Vec::new();
+
+        for hunk in diff.unified_diff().context_radius(CONTEXT).iter_hunks() {
+            lines.push(Line::Hunk(hunk.header().to_string()));
+
+            for change in hunk.iter_changes() {
+                let value = change.value().trim_end_matches('\n').to_owned();
+
+                lines.push(match change.tag() {
+                    ChangeTag::Equal => Line::Same(value),
+                    ChangeTag::Delete => Line::Removed(value),
+                    ChangeTag::Insert => Line::Added(value),
+                });
+            }
+        }
+
+        Self { name, lines }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lines.is_empty()
+    }
+
+    /// the panel under the editor showing the diff
+    pub fn view(&self, font_size: f32) -> Element<'_, Message> {
+        let header = row![
+            text(format!("Changes to {} since it was saved", self.name)).size(14),
+            horizontal_space(Length::Fill),
+            button(text("×").size(14)).style(theme::Button::Text).on_press(Message::CloseDiff)]
+            .align_items(Alignment::Center);
+
+        let lines = self.lines.iter().fold(column![], |lines, line| {
+            let line = match line {
+                Line::Hunk(header) => text(header).style(color!(0x61afef)),
+                Line::Same(value) => text(format!("  {value}")),
+                Line::Removed(value) => text(format!("- {value}")).style(color!(0xff5555)),
+                Line::Added(value) => text(format!("+ {value}")).style(color!(0x98c379)),
+            };
+
+            lines.push(line.font(Font::MONOSPACE).size(font_size * 0.9))
+        });
+
+        column![header, scrollable(lines).height(Length::Fixed(240.0))].spacing(5).into()
+    }
+}
@@ -0,0 +1,201 @@
+use std::path::PathBuf;
+
+use crate::{Error, LoadedFile};
+
+/// the widget-free state of an open document
+///
+/// transitions take the old state and return the new one, so they can be
+/// exercised without iced; `Buffer` converts to and from this, and the
+/// default is a new, empty document
+#[derive(Debug, Clone, Default)]
+pub struct Document {
+    pub path: Option<PathBuf>,
+    pub text: String,
+    pub is_dirty: bool,
+    pub error: Option<Error>,
+}
+
+/// what to tidy before writing a document
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SaveOptions {
+    pub trim_whitespace: bool,
+    pub final_newline: bool,
+}
+
+impl Document {
+    /// a document read from disk, or the old one with the error if reading failed
+    pub fn opened(self, result: Result<LoadedFile, Error>) -> Self {
+        match result {
+            Ok(file) => Self {
+                path: Some(file.path),
+                text: file.content.as_ref().clone(),
+                is_dirty: false,
+                error: None,
+            },
+            // cancelling the dialog is not a failure
+            Err(Error::DialogClosed) => self,
+            Err(error) => Self { error: Some(error), ..self },
+        }
+    }
+
+    /// the text as it should be written, tidied as `options` ask
+    pub fn prepared(self, options: SaveOptions) -> Self {
+        let mut text = self.text;
+
+        if options.trim_whitespace {
+            text = trim_whitespace(&text);
+        }
+
+        if options.final_newline {
+            text = final_newline(&text);
+        }
+
+        Self { text, ..self }
+    }
+
+    /// a clean document at the saved path, or the old one with the error
+    pub fn saved(self, result: Result<PathBuf, Error>) -> Self {
+        match result {
+            Ok(path) => Self {
+                path: Some(path),
+                is_dirty: false,
+                error: None,
+                ..self
+            },
+            Err(Error::DialogClosed) => self,
+            Err(error) => Self { error: Some(error), ..self },
+        }
+    }
+}
+
+/// strip spaces and tabs from the end of every line
+pub fn trim_whitespace(text: &str) -> String {
+    text.split('\n')
+        .map(|line| line.trim_end_matches([' ', '\t']))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// end a non-empty text with a newline
+pub fn final_newline(text: &str) -> String {
+    match text.is_empty() || text.ends_with('\n') {
+        true => text.to_owned(),
+        false => format!("{text}\n"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{encoding::Encoding, LineEnding};
+
+    fn dirty() -> Document {
+        Document { path: Some(PathBuf::from("old.txt")), text: String::from("edited"), is_dirty: true, error: None }
+    }
+
+    fn loaded(path: &str, content: &str) -> LoadedFile {
+        LoadedFile {
+            path: PathBuf::from(path),
+            content: Arc::new(content.to_owned()),
+            line_ending: LineEnding::default(),
+            encoding: Encoding::default(),
+            read_only: false,
+            modified: None,
+            project: PathBuf::new(),
+        }
+    }
+
+    fn failure() -> Error {
+        Error::UnknownEncoding
+    }
+
+    #[test]
+    fn new_is_empty_and_clean() {
+        let document = Document::default();
+
+        assert_eq!(document.path, None);
+        assert!(document.text.is_empty());
+        assert!(!document.is_dirty);
+    }
+
+    #[test]
+    fn opened_takes_the_file() {
+        let document = dirty().opened(Ok(loaded("new.txt", "on disk")));
+
+        assert_eq!(document.path, Some(PathBuf::from("new.txt")));
+        assert_eq!(document.text, "on disk");
+        assert!(!document.is_dirty);
+        assert!(document.error.is_none());
+    }
+
+    #[test]
+    fn opened_keeps_the_document_on_error() {
+        let document = dirty().opened(Err(failure()));
+
+        assert_eq!(document.path, Some(PathBuf::from("old.txt")));
+        assert_eq!(document.text, "edited");
+        assert!(document.is_dirty);
+        assert!(matches!(document.error, Some(Error::UnknownEncoding)));
+    }
+
+    #[test]
+    fn opened_ignores_a_closed_dialog() {
+        let document = dirty().opened(Err(Error::DialogClosed));
+
+        assert_eq!(document.text, "edited");
+        assert!(document.error.is_none());
+    }
+
+    #[test]
+    fn prepared_trims_and_ends_with_a_newline() {
+        let document = Document { text: String::from("a  \nb\t\nc "), ..Document::default() };
+
+        let trimmed = document.clone().prepared(SaveOptions { trim_whitespace: true, final_newline: false });
+        assert_eq!(trimmed.text, "a\nb\nc");
+
+        let ended = document.clone().prepared(SaveOptions { trim_whitespace: false, final_newline: true });
+        assert_eq!(ended.text, "a  \nb\t\nc \n");
+
+        let both = document.clone().prepared(SaveOptions { trim_whitespace: true, final_newline: true });
+        assert_eq!(both.text, "a\nb\nc\n");
+
+        let untouched = document.prepared(SaveOptions::default());
+        assert_eq!(untouched.text, "a  \nb\t\nc ");
+    }
+
+    #[test]
+    fn prepared_leaves_an_empty_text_empty() {
+        let document = Document::default().prepared(SaveOptions { trim_whitespace: true, final_newline: true });
+
+        assert!(document.text.is_empty());
+    }
+
+    #[test]
+    fn saved_cleans_the_document() {
+        let document = Document { error: Some(failure()), ..dirty() }.saved(Ok(PathBuf::from("saved.txt")));
+
+        assert_eq!(document.path, Some(PathBuf::from("saved.txt")));
+        assert_eq!(document.text, "edited");
+        assert!(!document.is_dirty);
+        assert!(document.error.is_none());
+    }
+
+    #[test]
+    fn saved_stays_dirty_on_error() {
+        let document = dirty().saved(Err(failure()));
+
+        assert_eq!(document.path, Some(PathBuf::from("old.txt")));
+        assert!(document.is_dirty);
+        assert!(matches!(document.error, Some(Error::UnknownEncoding)));
+    }
+
+    #[test]
+    fn saved_ignores_a_closed_dialog() {
+        let document = dirty().saved(Err(Error::DialogClosed));
+
+        assert!(document.is_dirty);
+        assert!(document.error.is_none());
+    }
+}
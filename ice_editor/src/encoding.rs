@@ -0,0 +1,162 @@
+use std::fmt;
+use encoding_rs::{UTF_16BE, UTF_16LE, UTF_8, WINDOWS_1252};
+
+/// text encoding of a file on disk
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Encoding {
+    #[default]
+    Utf8,
+    Utf8Bom,
+    Utf16Le,
+    Utf16Be,
+    Latin1,
+}
+
+impl Encoding {
+    /// encodings offered when saving, Latin-1 can't hold every char so it's left out
+    pub const SAVABLE: [Encoding; 4] = [
+        Encoding::Utf8,
+        Encoding::Utf8Bom,
+        Encoding::Utf16Le,
+        Encoding::Utf16Be,
+    ];
+
+    /// decode `bytes`, or `None` when the encoding can't be told apart
+    pub fn decode(bytes: &[u8]) -> Option<(String, Encoding)> {
+        if let Some((encoding, bom_length)) = encoding_rs::Encoding::for_bom(bytes) {
+            let kind = if encoding == UTF_8 {
+                Encoding::Utf8Bom
+            } else if encoding == UTF_16LE {
+                Encoding::Utf16Le
+            } else {
+                Encoding::Utf16Be
+            };
+
+            return decode_strict(kind, &bytes[bom_length..]).map(|text| (text, kind));
+        }
+
+        if let Ok(text) = std::str::from_utf8(bytes) {
+            return Some((text.to_owned(), Encoding::Utf8));
+        }
+
+        if let Some(kind) = guess_utf16(bytes) {
+            return decode_strict(kind, bytes).map(|text| (text, kind));
+        }
+
+        // NULs in something that isn't UTF-16 means binary or an encoding we don't know
+        if bytes.contains(&0) {
+            return None;
+        }
+
+        decode_strict(Encoding::Latin1, bytes).map(|text| (text, Encoding::Latin1))
+    }
+
+    /// encode `text`, writing a BOM for the encodings that carry one
+    pub fn encode(self, text: &str) -> Vec<u8> {
+        match self {
+            Encoding::Utf8 => text.as_bytes().to_vec(),
+            Encoding::Utf8Bom => [&[0xEF, 0xBB, 0xBF][..], text.as_bytes()].concat(),
+            // encoding_rs can only decode UTF-16, so we encode it by hand
+            Encoding::Utf16Le => [0xFEFF].into_iter()
+                .chain(text.encode_utf16())
+                .flat_map(u16::to_le_bytes)
+                .collect(),
+            Encoding::Utf16Be => [0xFEFF].into_iter()
+                .chain(text.encode_utf16())
+                .flat_map(u16::to_be_bytes)
+                .collect(),
+            Encoding::Latin1 => WINDOWS_1252.encode(text).0.into_owned(),
+        }
+    }
+}
+
+impl fmt::Display for Encoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Encoding::Utf8 => "UTF-8",
+            Encoding::Utf8Bom => "UTF-8 with BOM",
+            Encoding::Utf16Le => "UTF-16LE",
+            Encoding::Utf16Be => "UTF-16BE",
+            Encoding::Latin1 => "Latin-1",
+        })
+    }
+}
+
+/// decode without a BOM, failing on malformed input
+fn decode_strict(kind: Encoding, bytes: &[u8]) -> Option<String> {
+    let encoding = match kind {
+        Encoding::Utf8 | Encoding::Utf8Bom => UTF_8,
+        Encoding::Utf16Le => UTF_16LE,
+        Encoding::Utf16Be => UTF_16BE,
+        Encoding::Latin1 => WINDOWS_1252,
+    };
+
+    encoding
+        .decode_without_bom_handling_and_without_replacement(bytes)
+        .map(|text| text.into_owned())
+}
+
+/// BOM-less UTF-16 shows up as mostly-ASCII text with NULs on one side of each pair
+fn guess_utf16(bytes: &[u8]) -> Option<Encoding> {
+    if bytes.len() < 2 || bytes.len() % 2 != 0 {
+        return None;
+    }
+
+    let pairs = bytes.len() / 2;
+    let (even, odd) = bytes.chunks_exact(2).fold((0, 0), |(even, odd), pair| {
+        (even + usize::from(pair[0] == 0), odd + usize::from(pair[1] == 0))
+    });
+
+    if odd > pairs / 2 && even == 0 {
+        Some(Encoding::Utf16Le)
+    } else if even > pairs / 2 && odd == 0 {
+        Some(Encoding::Utf16Be)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// save and open again, checking the text and encoding both survive
+    fn round_trip(encoding: Encoding, text: &str) -> Vec<u8> {
+        let bytes = encoding.encode(text);
+        let (decoded, detected) = Encoding::decode(&bytes).expect("encoded text should decode");
+
+        assert_eq!(decoded, text);
+        assert_eq!(detected, encoding);
+        bytes
+    }
+
+    #[test]
+    fn utf8() {
+        let bytes = round_trip(Encoding::Utf8, "héllo wörld ☕ 你好\n");
+        assert!(!bytes.starts_with(&[0xEF, 0xBB, 0xBF]));
+    }
+
+    #[test]
+    fn utf8_bom() {
+        let bytes = round_trip(Encoding::Utf8Bom, "héllo wörld ☕ 你好\n");
+        assert!(bytes.starts_with(&[0xEF, 0xBB, 0xBF]));
+    }
+
+    #[test]
+    fn utf16_le() {
+        let bytes = round_trip(Encoding::Utf16Le, "héllo wörld ☕ 你好\n");
+        assert!(bytes.starts_with(&[0xFF, 0xFE]));
+    }
+
+    #[test]
+    fn utf16_be() {
+        let bytes = round_trip(Encoding::Utf16Be, "héllo wörld ☕ 你好\n");
+        assert!(bytes.starts_with(&[0xFE, 0xFF]));
+    }
+
+    #[test]
+    fn latin1() {
+        let bytes = round_trip(Encoding::Latin1, "café déjà vu\n");
+        assert!(bytes.contains(&0xE9));
+    }
+}
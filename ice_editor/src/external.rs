@@ -0,0 +1,105 @@
+use std::{path::{Path, PathBuf}, process::Stdio};
+use iced::{
+    futures::future,
+    theme,
+    widget::{button, column, horizontal_space, row, scrollable, text},
+    Alignment, Element, Length,
+};
+use tokio::io::AsyncWriteExt;
+
+use crate::{Error, Message};
+
+/// what a finished external command printed
+#[derive(Debug, Clone)]
+pub struct Output {
+    pub command: String,
+    pub success: bool,
+    pub text: String, // stdout, then stderr
+}
+
+/// run `template` on `path`, from the file's directory
+///
+/// the template is split on whitespace before `{path}` is filled in, so a
+/// path with spaces stays a single argument
+pub async fn run(template: String, path: PathBuf) -> Result<Output, Error> {
+    let path_text = path.display().to_string();
+    let mut args = template.split_whitespace().map(|arg| arg.replace("{path}", &path_text));
+    let command = template.replace("{path}", &path_text);
+
+    let Some(program) = args.next() else {
+        return Err(Error::IO { action: "run an external command on", path, kind: std::io::ErrorKind::InvalidInput });
+    };
+
+    let output = tokio::process::Command::new(&program)
+        .args(args)
+        .current_dir(path.parent().unwrap_or(Path::new(".")))
+        .output()
+        .await
+        .map_err(Error::io("run an external command on", &path))?;
+
+    let mut text = String::from_utf8_lossy(&output.stdout).into_owned();
+    text.push_str(&String::from_utf8_lossy(&output.stderr));
+
+    if !output.status.success() && text.trim().is_empty() {
+        text = format!("{program} exited with {}", output.status);
+    }
+
+    Ok(Output { command, success: output.status.success(), text })
+}
+
+/// pipe `text` through a formatter `command`, the error is what it complained about
+pub async fn format(command: String, text: String) -> Result<String, String> {
+    let mut args = command.split_whitespace();
+    let program = args.next().ok_or_else(|| String::from("the formatter command is empty"))?;
+
+    let mut child = tokio::process::Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|error| format!("couldn't run {program}: {error}"))?;
+
+    // write while the output is read, a big file could fill the pipe otherwise
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    let write = async move {
+        let _ = stdin.write_all(text.as_bytes()).await;
+        // dropping stdin closes it, so the formatter knows the input is done
+    };
+    let ((), output) = future::join(write, child.wait_with_output()).await;
+    let output = output.map_err(|error| format!("{program} failed: {error}"))?;
+
+    if output.status.success() {
+        return String::from_utf8(output.stdout).map_err(|_| format!("{program} printed invalid UTF-8"));
+    }
+
+    // the first error is the useful one, the rest is usually context
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let reason = stderr
+        .lines()
+        .find(|line| line.starts_with("error"))
+        .or_else(|| stderr.lines().find(|line| !line.trim().is_empty()))
+        .map_or_else(|| format!("{program} exited with {}", output.status), str::to_owned);
+
+    Err(reason)
+}
+
+impl Output {
+    /// the panel under the editor showing the output
+    pub fn view(&self) -> Element<'_, Message> {
+        let status = match self.success {
+            true => format!("{} finished", self.command),
+            false => format!("{} failed", self.command),
+        };
+
+        let header = row![
+            text(status).size(14),
+            horizontal_space(Length::Fill),
+            button(text("×").size(14)).style(theme::Button::Text).on_press(Message::CloseExternalOutput)]
+            .align_items(Alignment::Center);
+
+        column![header, scrollable(text(self.text.trim_end()).size(13)).height(Length::Fixed(160.0))]
+            .spacing(5)
+            .into()
+    }
+}
@@ -0,0 +1,161 @@
+use std::path::{Path, PathBuf};
+use iced::{
+    futures::{stream, StreamExt},
+    theme,
+    widget::{button, column, horizontal_space, row, scrollable, text},
+    Alignment, Element, Length,
+};
+
+use crate::{encoding::Encoding, search::Pattern, Message};
+
+/// how many files are read at the same time
+const CONCURRENCY: usize = 8;
+/// matches past this many aren't collected
+const MAX_HITS: usize = 1000;
+/// longest bit of a matching line shown in the results
+const MAX_PREVIEW: usize = 200;
+
+/// state of the search across the open folder
+#[derive(Debug, Clone, Default)]
+pub struct FindInFiles {
+    pub query: String,
+    pub found: Option<Found>, // `None` until a search finishes, or once the results are closed
+    pub searching: bool,
+}
+
+/// what a search turned up
+#[derive(Debug, Clone, Default)]
+pub struct Found {
+    pub hits: Vec<Hit>,
+    pub skipped: usize, // binary, oversized or unreadable files
+    pub truncated: bool, // stopped at `MAX_HITS`
+}
+
+/// a line of a file with a match on it
+#[derive(Debug, Clone)]
+pub struct Hit {
+    pub path: PathBuf,
+    pub line: usize,   // 0-based
+    pub column: usize, // of the first match, in chars
+    pub text: String,
+}
+
+/// every line under `root` that `pattern` matches, files in path order
+///
+/// dot directories such as `.git` are left out
+pub async fn search(root: PathBuf, pattern: Pattern, max_size: u64) -> Found {
+    let mut found = Found::default();
+    let mut files = stream::iter(files(root).await)
+        .map(|path| search_file(path, &pattern, max_size))
+        .buffered(CONCURRENCY);
+
+    while let Some(hits) = files.next().await {
+        match hits {
+            Some(hits) => found.hits.extend(hits),
+            None => found.skipped += 1,
+        }
+
+        if found.hits.len() >= MAX_HITS {
+            found.hits.truncate(MAX_HITS);
+            found.truncated = true;
+            break;
+        }
+    }
+
+    found
+}
+
+/// every file under `root`, sorted
+async fn files(root: PathBuf) -> Vec<PathBuf> {
+    let mut dirs = vec![root];
+    let mut files = Vec::new();
+
+    while let Some(dir) = dirs.pop() {
+        let Ok(mut entries) = tokio::fs::read_dir(&dir).await else {
+            continue;
+        };
+
+        while let Ok(Some(entry)) = entries.next_entry().await {
+            if entry.file_name().to_string_lossy().starts_with('.') {
+                continue;
+            }
+
+            match entry.file_type().await {
+                Ok(file_type) if file_type.is_dir() => dirs.push(entry.path()),
+                Ok(file_type) if file_type.is_file() => files.push(entry.path()),
+                _ => {},
+            }
+        }
+    }
+
+    files.sort();
+    files
+}
+
+/// the matching lines of one file, `None` if it was skipped
+async fn search_file(path: PathBuf, pattern: &Pattern, max_size: u64) -> Option<Vec<Hit>> {
+    if tokio::fs::metadata(&path).await.ok()?.len() > max_size {
+        return None;
+    }
+
+    let bytes = tokio::fs::read(&path).await.ok()?;
+    let (content, _) = Encoding::decode(&bytes)?;
+
+    // Latin-1 takes any bytes, but text files don't have NULs
+    if content.contains('\0') {
+        return None;
+    }
+
+    let hits = content
+        .lines()
+        .enumerate()
+        .filter_map(|(line, text)| {
+            let first = pattern.find_all(text).into_iter().next()?;
+
+            Some(Hit {
+                path: path.clone(),
+                line,
+                column: text[..first.start].chars().count(),
+                text: text.trim().chars().take(MAX_PREVIEW).collect(),
+            })
+        })
+        .collect();
+
+    Some(hits)
+}
+
+impl FindInFiles {
+    /// the results panel, with paths shown relative to `root`
+    pub fn view(&self, root: &Path) -> Option<Element<'_, Message>> {
+        let found = self.found.as_ref()?;
+
+        let mut files: Vec<_> = found.hits.iter().map(|hit| &hit.path).collect();
+        files.dedup();
+
+        let mut summary = format!("{} matches in {} files", found.hits.len(), files.len());
+        if found.truncated {
+            summary.push_str(", stopped there");
+        }
+        if found.skipped > 0 {
+            summary.push_str(&format!(", {} skipped", found.skipped));
+        }
+
+        let header = row![
+            text(summary).size(14),
+            horizontal_space(Length::Fill),
+            button(text("×").size(14)).style(theme::Button::Text).on_press(Message::CloseFindInFiles)]
+            .align_items(Alignment::Center);
+
+        let hits = found.hits.iter().fold(column![].spacing(2), |hits, hit| {
+            let path = hit.path.strip_prefix(root).unwrap_or(&hit.path);
+
+            hits.push(
+                button(text(format!("{}:{}  {}", path.display(), hit.line + 1, hit.text)).size(13))
+                    .style(theme::Button::Text)
+                    .width(Length::Fill)
+                    .on_press(Message::OpenHit(hit.path.clone(), (hit.line + 1, hit.column + 1))))
+        });
+
+        Some(column![header, scrollable(hits).height(Length::Fixed(200.0))].spacing(5).into())
+    }
+}
@@ -0,0 +1,49 @@
+use std::path::PathBuf;
+use iced::Font;
+
+/// the editor font: the family from the settings, else the default monospace
+///
+/// iced wants a `'static` name, and the font is only picked once at startup,
+/// so the name is leaked once
+pub fn editor_font(family: Option<&str>) -> Font {
+    match family.map(str::trim) {
+        Some(family) if !family.is_empty() => Font::with_name(Box::leak(family.to_owned().into_boxed_str())),
+        _ => Font::MONOSPACE,
+    }
+}
+
+/// whether a font file for `family` is in one of the usual font folders
+///
+/// the text engine quietly falls back when a family is missing, so this is
+/// only used to warn about it; matching is by file name, which is a guess
+pub async fn is_installed(family: String) -> bool {
+    let wanted = normalize(&family);
+    let mut dirs: Vec<PathBuf> = dirs::font_dir().into_iter().collect();
+    dirs.extend(
+        ["/usr/share/fonts", "/usr/local/share/fonts", "/Library/Fonts", "/System/Library/Fonts", "C:\\Windows\\Fonts"]
+            .map(PathBuf::from),
+    );
+
+    while let Some(dir) = dirs.pop() {
+        let Ok(mut entries) = tokio::fs::read_dir(&dir).await else {
+            continue;
+        };
+
+        while let Ok(Some(entry)) = entries.next_entry().await {
+            let path = entry.path();
+
+            if entry.file_type().await.is_ok_and(|file_type| file_type.is_dir()) {
+                dirs.push(path);
+            } else if path.file_stem().is_some_and(|stem| normalize(&stem.to_string_lossy()).starts_with(&wanted)) {
+                return true;
+            }
+        }
+    }
+
+    false
+}
+
+/// `Fira Code` and `FiraCode-Regular` should compare equal
+fn normalize(name: &str) -> String {
+    name.chars().filter(char::is_ascii_alphanumeric).map(|c| c.to_ascii_lowercase()).collect()
+}
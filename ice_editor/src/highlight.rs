@@ -0,0 +1,273 @@
+use std::{fmt, ops::Range, path::Path};
+use iced::{advanced::text::{self, highlighter::Format, Highlighter as _}, highlighter, Color, Font};
+use serde::{Deserialize, Serialize};
+
+use crate::search::Pattern;
+
+/// syntax used to highlight a buffer
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Language {
+    #[default]
+    PlainText,
+    Rust,
+    Markdown,
+    Python,
+    JavaScript,
+    Json,
+    Html,
+    Css,
+    C,
+    Cpp,
+    Go,
+    Shell,
+    Yaml,
+}
+
+impl Language {
+    pub const ALL: [Language; 13] = [
+        Language::PlainText,
+        Language::Rust,
+        Language::Markdown,
+        Language::Python,
+        Language::JavaScript,
+        Language::Json,
+        Language::Html,
+        Language::Css,
+        Language::C,
+        Language::Cpp,
+        Language::Go,
+        Language::Shell,
+        Language::Yaml,
+    ];
+
+    /// guess the language from a file extension, plain text when unknown
+    pub fn detect(path: Option<&Path>) -> Self {
+        let extension = path
+            .and_then(Path::extension)
+            .and_then(|extension| extension.to_str())
+            .map(str::to_lowercase)
+            .unwrap_or_default();
+
+        match extension.as_str() {
+            "rs" => Language::Rust,
+            "md" | "markdown" => Language::Markdown,
+            "py" => Language::Python,
+            "js" | "mjs" | "cjs" => Language::JavaScript,
+            "json" => Language::Json,
+            "html" | "htm" => Language::Html,
+            "css" => Language::Css,
+            "c" | "h" => Language::C,
+            "cpp" | "cc" | "cxx" | "hpp" | "hh" => Language::Cpp,
+            "go" => Language::Go,
+            "sh" | "bash" | "zsh" => Language::Shell,
+            "yml" | "yaml" => Language::Yaml,
+            // the bundled syntaxes have no TOML grammar, so it stays plain
+            _ => Language::PlainText,
+        }
+    }
+
+    /// token the highlighter looks the syntax up by
+    fn token(self) -> &'static str {
+        match self {
+            Language::PlainText => "txt",
+            Language::Rust => "rs",
+            Language::Markdown => "md",
+            Language::Python => "py",
+            Language::JavaScript => "js",
+            Language::Json => "json",
+            Language::Html => "html",
+            Language::Css => "css",
+            Language::C => "c",
+            Language::Cpp => "cpp",
+            Language::Go => "go",
+            Language::Shell => "sh",
+            Language::Yaml => "yaml",
+        }
+    }
+}
+
+impl fmt::Display for Language {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Language::PlainText => "Plain Text",
+            Language::Rust => "Rust",
+            Language::Markdown => "Markdown",
+            Language::Python => "Python",
+            Language::JavaScript => "JavaScript",
+            Language::Json => "JSON",
+            Language::Html => "HTML",
+            Language::Css => "CSS",
+            Language::C => "C",
+            Language::Cpp => "C++",
+            Language::Go => "Go",
+            Language::Shell => "Shell",
+            Language::Yaml => "YAML",
+        })
+    }
+}
+
+/// color scheme for code, the bundled ones of the highlighter
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum SyntaxTheme {
+    #[default]
+    MatchUi, // follows the light or dark UI theme
+    SolarizedDark,
+    Base16Mocha,
+    Base16Ocean,
+    Base16Eighties,
+    InspiredGitHub,
+}
+
+impl SyntaxTheme {
+    pub const ALL: [SyntaxTheme; 6] = [
+        SyntaxTheme::MatchUi,
+        SyntaxTheme::SolarizedDark,
+        SyntaxTheme::Base16Mocha,
+        SyntaxTheme::Base16Ocean,
+        SyntaxTheme::Base16Eighties,
+        SyntaxTheme::InspiredGitHub,
+    ];
+
+    fn theme(self, dark: bool) -> highlighter::Theme {
+        match self {
+            SyntaxTheme::MatchUi if dark => highlighter::Theme::Base16Mocha,
+            SyntaxTheme::MatchUi => highlighter::Theme::InspiredGitHub,
+            SyntaxTheme::SolarizedDark => highlighter::Theme::SolarizedDark,
+            SyntaxTheme::Base16Mocha => highlighter::Theme::Base16Mocha,
+            SyntaxTheme::Base16Ocean => highlighter::Theme::Base16Ocean,
+            SyntaxTheme::Base16Eighties => highlighter::Theme::Base16Eighties,
+            SyntaxTheme::InspiredGitHub => highlighter::Theme::InspiredGitHub,
+        }
+    }
+}
+
+impl fmt::Display for SyntaxTheme {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            SyntaxTheme::MatchUi => "Match UI",
+            SyntaxTheme::SolarizedDark => "Solarized Dark",
+            SyntaxTheme::Base16Mocha => "Mocha",
+            SyntaxTheme::Base16Ocean => "Ocean",
+            SyntaxTheme::Base16Eighties => "Eighties",
+            SyntaxTheme::InspiredGitHub => "GitHub",
+        })
+    }
+}
+
+/// highlighter settings for `language` in the `theme` colors
+pub fn settings(language: Language, theme: SyntaxTheme, dark: bool) -> highlighter::Settings {
+    highlighter::Settings {
+        theme: theme.theme(dark),
+        extension: language.token().to_owned(),
+    }
+}
+
+/// the syntax highlighting, plus the search matches on top
+#[derive(Debug, Clone, PartialEq)]
+pub struct Settings {
+    pub syntax: highlighter::Settings,
+    pub query: String, // empty while the search bar is closed
+    pub case_sensitive: bool,
+    pub regex: bool,
+    pub match_color: Color,
+    pub cursors: Vec<(usize, Range<usize>)>, // extra cursors, as a line and the bytes on it
+}
+
+/// wraps the syntax highlighter to paint every search match and extra cursor
+///
+/// matches are found line by line as the editor asks for them, so only the
+/// visible part of a big file is ever searched for the highlights
+pub struct Highlighter {
+    syntax: highlighter::Highlighter,
+    settings: Settings,
+    pattern: Option<Pattern>, // `None` while the query is empty or invalid
+}
+
+/// the pattern to paint, if there is a usable one
+fn pattern(settings: &Settings) -> Option<Pattern> {
+    if settings.query.is_empty() {
+        return None;
+    }
+
+    Pattern::new(&settings.query, settings.case_sensitive, settings.regex).ok()
+}
+
+impl text::Highlighter for Highlighter {
+    type Settings = Settings;
+    type Highlight = Format<Font>;
+    type Iterator<'a> = std::vec::IntoIter<(Range<usize>, Format<Font>)>;
+
+    fn new(settings: &Settings) -> Self {
+        Self {
+            syntax: highlighter::Highlighter::new(&settings.syntax),
+            settings: settings.clone(),
+            pattern: pattern(settings),
+        }
+    }
+
+    fn update(&mut self, new_settings: &Settings) {
+        if new_settings.syntax != self.settings.syntax {
+            self.syntax.update(&new_settings.syntax);
+        } else {
+            // same syntax, but the matches moved: paint everything again
+            self.syntax.change_line(0);
+        }
+
+        self.settings = new_settings.clone();
+        self.pattern = pattern(new_settings);
+    }
+
+    fn change_line(&mut self, line: usize) {
+        self.syntax.change_line(line);
+    }
+
+    fn highlight_line(&mut self, line: &str) -> Self::Iterator<'_> {
+        let number = self.syntax.current_line();
+        let spans: Vec<_> = self
+            .syntax
+            .highlight_line(line)
+            .map(|(range, highlight)| (range, highlight.to_format()))
+            .collect();
+        let mut matches = self.pattern.as_ref().map(|pattern| pattern.find_all(line)).unwrap_or_default();
+
+        matches.extend(
+            self.settings.cursors.iter()
+                .filter(|(cursor_line, range)| *cursor_line == number && range.end <= line.len())
+                .map(|(_, range)| range.clone()),
+        );
+        matches.sort_by_key(|found| found.start);
+        // a cursor on a match is painted once
+        matches.dedup_by(|next, previous| next.start < previous.end);
+
+        if matches.is_empty() {
+            return spans.into_iter();
+        }
+
+        // cut the matches out of the syntax spans, so the two never overlap
+        let mut result = Vec::new();
+        for (range, format) in spans {
+            let mut start = range.start;
+
+            for found in matches.iter().filter(|found| found.start < range.end && found.end > range.start) {
+                if found.start > start {
+                    result.push((start..found.start, format));
+                }
+                start = start.max(found.end);
+            }
+
+            if start < range.end {
+                result.push((start..range.end, format));
+            }
+        }
+
+        let format = Format { color: Some(self.settings.match_color), font: None };
+        result.extend(matches.into_iter().map(|found| (found, format)));
+        result.sort_by_key(|(range, _)| range.start);
+
+        result.into_iter()
+    }
+
+    fn current_line(&self) -> usize {
+        self.syntax.current_line()
+    }
+}
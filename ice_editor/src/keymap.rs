@@ -0,0 +1,220 @@
+use std::collections::{BTreeMap, HashMap};
+use iced::keyboard::{KeyCode, Modifiers};
+
+use crate::{palette, Message};
+
+/// shortcuts used when the settings don't say otherwise, as combo and command id
+const DEFAULTS: &[(&str, &str)] = &[
+    ("Ctrl+N", "new"),
+    ("Ctrl+Shift+N", "new_window"),
+    ("Ctrl+O", "open"),
+    ("Ctrl+Shift+T", "reopen_closed_tab"),
+    ("Ctrl+S", "save"),
+    ("Ctrl+Shift+S", "save_as"),
+    ("F5", "reload"),
+    ("Ctrl+Shift+R", "run_external"),
+    ("Ctrl+Z", "undo"),
+    ("Ctrl+Shift+Z", "redo"),
+    // Ctrl+D is the usual multi-cursor key, so duplicating a line took Ctrl+Shift+D
+    ("Ctrl+D", "add_next_occurrence"),
+    ("Ctrl+Shift+V", "paste_from_history"),
+    ("Ctrl+Shift+D", "duplicate_line"),
+    ("Ctrl+Shift+K", "delete_line"),
+    ("Ctrl+L", "select_line"),
+    ("Ctrl+M", "jump_to_bracket"),
+    ("Ctrl+A", "select_all"),
+    ("Ctrl+Slash", "toggle_comment"),
+    ("Ctrl+F", "find"),
+    ("Ctrl+G", "go_to_line"),
+    ("Ctrl+Shift+P", "palette"),
+    ("Alt+Z", "toggle_word_wrap"),
+    ("F11", "toggle_zen"),
+    ("Ctrl+Equals", "zoom_in"),
+    ("Ctrl+Plus", "zoom_in"),
+    ("Ctrl+NumpadAdd", "zoom_in"),
+    ("Ctrl+Minus", "zoom_out"),
+    ("Ctrl+NumpadSubtract", "zoom_out"),
+    ("Ctrl+0", "reset_zoom"),
+    ("Ctrl+Numpad0", "reset_zoom"),
+];
+
+/// a key with the modifiers that must be held, nothing more and nothing less
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct Combo {
+    key: KeyCode,
+    command: bool, // ctrl, or cmd on macOS
+    shift: bool,
+    alt: bool,
+}
+
+/// key combos mapped to command ids
+#[derive(Debug, Clone)]
+pub struct Keymap {
+    bindings: HashMap<Combo, &'static str>,
+    pub warnings: Vec<String>, // bindings from the settings that were ignored
+}
+
+impl Keymap {
+    /// the defaults, overridden by the `combo -> command` pairs from the settings
+    ///
+    /// a bad or conflicting user binding is skipped with a warning, so the
+    /// defaults still work
+    pub fn new(user: &BTreeMap<String, String>) -> Self {
+        let mut bindings: HashMap<_, _> = DEFAULTS
+            .iter()
+            .filter_map(|(combo, id)| Some((parse(combo)?, *id)))
+            .collect();
+        let mut warnings = Vec::new();
+        let mut wanted: HashMap<Combo, Vec<(&str, &'static str)>> = HashMap::new();
+
+        for (text, id) in user {
+            let Some(combo) = parse(text) else {
+                warnings.push(format!("unknown key combo \"{text}\""));
+                continue;
+            };
+            let Some(id) = palette::commands().into_iter().map(|entry| entry.id).find(|known| *known == id.as_str()) else {
+                warnings.push(format!("unknown command \"{id}\" for {text}"));
+                continue;
+            };
+
+            wanted.entry(combo).or_default().push((text.as_str(), id));
+        }
+
+        // "ctrl+s" and "Ctrl+S" are the same combo, and we can't tell which one was meant
+        for (combo, entries) in wanted {
+            match entries[..] {
+                [(_, id)] => {
+                    bindings.insert(combo, id);
+                },
+                _ => {
+                    let texts: Vec<_> = entries.iter().map(|(text, _)| *text).collect();
+                    warnings.push(format!("{} are the same keys, keeping the default", texts.join(" and ")));
+                },
+            }
+        }
+
+        Self { bindings, warnings }
+    }
+
+    /// the message bound to a key press, if any
+    pub fn get(&self, key: KeyCode, modifiers: Modifiers) -> Option<Message> {
+        let combo = Combo {
+            key,
+            command: modifiers.command(),
+            shift: modifiers.shift(),
+            alt: modifiers.alt(),
+        };
+
+        self.bindings.get(&combo).and_then(|id| palette::command(id))
+    }
+}
+
+/// read a combo like `Ctrl+Shift+S`, case doesn't matter
+fn parse(text: &str) -> Option<Combo> {
+    let mut parts: Vec<_> = text.split('+').map(str::trim).collect();
+
+    // `Ctrl++` ends in an empty part, meaning the plus key
+    if text.trim_end().ends_with("++") {
+        parts.pop();
+        parts.pop();
+        parts.push("Plus");
+    }
+
+    let (key, modifiers) = parts.split_last()?;
+    let mut combo = Combo { key: key_code(key)?, command: false, shift: false, alt: false };
+
+    for modifier in modifiers {
+        match modifier.to_ascii_lowercase().as_str() {
+            "ctrl" | "cmd" | "control" | "command" => combo.command = true,
+            "shift" => combo.shift = true,
+            "alt" | "option" => combo.alt = true,
+            _ => return None,
+        }
+    }
+
+    Some(combo)
+}
+
+/// the key called `name`: a letter, digit, F-key or one of a few named keys
+fn key_code(name: &str) -> Option<KeyCode> {
+    const LETTERS: [KeyCode; 26] = [
+        KeyCode::A, KeyCode::B, KeyCode::C, KeyCode::D, KeyCode::E, KeyCode::F, KeyCode::G,
+        KeyCode::H, KeyCode::I, KeyCode::J, KeyCode::K, KeyCode::L, KeyCode::M, KeyCode::N,
+        KeyCode::O, KeyCode::P, KeyCode::Q, KeyCode::R, KeyCode::S, KeyCode::T, KeyCode::U,
+        KeyCode::V, KeyCode::W, KeyCode::X, KeyCode::Y, KeyCode::Z,
+    ];
+    const DIGITS: [KeyCode; 10] = [
+        KeyCode::Key0, KeyCode::Key1, KeyCode::Key2, KeyCode::Key3, KeyCode::Key4,
+        KeyCode::Key5, KeyCode::Key6, KeyCode::Key7, KeyCode::Key8, KeyCode::Key9,
+    ];
+    const F_KEYS: [KeyCode; 12] = [
+        KeyCode::F1, KeyCode::F2, KeyCode::F3, KeyCode::F4, KeyCode::F5, KeyCode::F6,
+        KeyCode::F7, KeyCode::F8, KeyCode::F9, KeyCode::F10, KeyCode::F11, KeyCode::F12,
+    ];
+
+    let lower = name.to_ascii_lowercase();
+    let mut chars = lower.chars();
+
+    match (chars.next(), chars.next()) {
+        (Some(c @ 'a'..='z'), None) => return Some(LETTERS[(c as u8 - b'a') as usize]),
+        (Some(c @ '0'..='9'), None) => return Some(DIGITS[(c as u8 - b'0') as usize]),
+        _ => {},
+    }
+
+    if let Some(number) = lower.strip_prefix('f').and_then(|n| n.parse::<usize>().ok()) {
+        return F_KEYS.get(number.checked_sub(1)?).copied();
+    }
+
+    Some(match lower.as_str() {
+        "plus" => KeyCode::Plus,
+        "minus" => KeyCode::Minus,
+        "equals" => KeyCode::Equals,
+        "numpadadd" => KeyCode::NumpadAdd,
+        "numpadsubtract" => KeyCode::NumpadSubtract,
+        "numpad0" => KeyCode::Numpad0,
+        "up" => KeyCode::Up,
+        "down" => KeyCode::Down,
+        "left" => KeyCode::Left,
+        "right" => KeyCode::Right,
+        "home" => KeyCode::Home,
+        "end" => KeyCode::End,
+        "pageup" => KeyCode::PageUp,
+        "pagedown" => KeyCode::PageDown,
+        "tab" => KeyCode::Tab,
+        "escape" | "esc" => KeyCode::Escape,
+        "enter" | "return" => KeyCode::Enter,
+        "space" => KeyCode::Space,
+        "slash" | "/" => KeyCode::Slash,
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ctrl_s_saves() {
+        let keymap = Keymap::new(&BTreeMap::new());
+
+        // ctrl everywhere but macOS, where it's cmd
+        assert!(matches!(keymap.get(KeyCode::S, Modifiers::COMMAND), Some(Message::Save)));
+    }
+
+    #[test]
+    fn unbound_combo_does_nothing() {
+        let keymap = Keymap::new(&BTreeMap::new());
+
+        assert!(keymap.get(KeyCode::Q, Modifiers::COMMAND | Modifiers::ALT).is_none());
+        assert!(keymap.get(KeyCode::S, Modifiers::empty()).is_none());
+    }
+
+    #[test]
+    fn user_bindings_override_the_defaults() {
+        let user = BTreeMap::from([(String::from("Ctrl+S"), String::from("save_as"))]);
+        let keymap = Keymap::new(&user);
+
+        assert!(keymap.warnings.is_empty());
+        assert!(matches!(keymap.get(KeyCode::S, Modifiers::COMMAND), Some(Message::SaveAs)));
+    }
+}
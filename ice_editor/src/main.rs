@@ -1,40 +1,346 @@
+mod buffer;
+mod comments;
+mod diff;
+mod editor_core;
+mod encoding;
+mod external;
+mod find_in_files;
+mod fonts;
+mod highlight;
+mod keymap;
+mod minimap;
+mod overlay;
+mod palette;
+mod preview;
+mod recovery;
+mod scripting;
+mod search;
+mod session;
+mod settings;
+mod tree;
+mod validate;
+mod watch;
+
 use std::{
+    collections::HashMap,
+    fmt,
     io,
+    time::{Duration, Instant, SystemTime},
     path::{
         Path, PathBuf
     }, sync::Arc
 };
-use tokio::fs;
+use tokio::{fs, io::AsyncReadExt};
+use buffer::Buffer;
+use encoding::Encoding;
+use highlight::Language;
 use iced::{
-        color, executor, widget::{
+        alignment, color, event, executor, keyboard, subscription, theme, time, widget::{
             button,
+            canvas,
+            checkbox,
             column,
             container,
             horizontal_space,
+            pick_list,
             row,
+            scrollable,
             text,
             text_editor,
-        }, Application, Command, Element, Length, Settings, Theme
+            text_input,
+            vertical_space,
+        }, window, Alignment, Application, Command, Element, Event, Font, Length, Settings, Subscription, Theme
     };
 
 fn main() -> iced::Result{
-    Editor::run(Settings::default())
+    Editor::run(Settings {
+        exit_on_close_request: false, // we ask about unsaved changes first
+        ..Settings::default()
+    })
 }
 
+const DEFAULT_FONT_SIZE: f32 = 14.0;
+const MIN_FONT_SIZE: f32 = 8.0;
+const MAX_FONT_SIZE: f32 = 48.0;
+const ZOOM_STEP: f32 = 2.0;
+const MAX_RECENT: usize = 10;
+const MAX_CLOSED: usize = 20; // closed tabs Ctrl+Shift+T can bring back
+const NOTICE_DURATION: Duration = Duration::from_secs(2);
+const WARNING_DURATION: Duration = Duration::from_secs(8);
+const STDIN_ARG: &str = "-"; // `cat foo | crab -`
+const NEW_WINDOW_FLAG: &str = "--new-window"; // start empty, not on the last file
+const CHAR_WIDTH: f32 = 0.6; // rough glyph advance, relative to the font size
+const LINE_HEIGHT: f32 = 1.3; // likewise for a line
+
 struct Editor {
-    path: Option<PathBuf>,
-    content: text_editor::Content,
-    error: Option<Error>,
+    buffers: Vec<Buffer>,
+    active: usize,
+    next_id: usize, // ids stay stable while tabs open and close
+    dialog_open: bool,
+    modifiers: keyboard::Modifiers, // held keys, which the editor's own key handling hides from us
+    settings: settings::Settings,
+    keymap: keymap::Keymap,
+    font: Font, // picked once at startup
+    search: search::Search,
+    go_to_line: Option<GoToLine>,
+    palette: Option<palette::Palette>,
+    preview: Option<preview::Preview>, // shown beside Markdown buffers
+    validation: validate::Validation, // parse errors of JSON and TOML buffers
+    find_in_files: find_in_files::FindInFiles,
+    external_output: Option<external::Output>,
+    diff: Option<diff::Diff>,
+    generation: usize, // stamped on loads and saves, so a late result can tell it was overtaken
+    opening: Option<usize>, // generation of the file being opened, `None` once it's done or cancelled
+    mirrored: HashMap<usize, usize>, // buffer id -> revision of its latest recovery copy
+    closed: Vec<(PathBuf, (usize, usize))>, // closed files and their cursors, the latest last
+    split: bool, // two panes on the active buffer
+    zen: bool, // only the editor and the bars it needs, no tabs, buttons or status bar
+    tree: Option<tree::Node>, // the open folder, shown in the sidebar
+    window_height: f32,
+    notice: Option<(String, Instant)>, // transient status message, shown until the instant
+    clipboard: Vec<String>, // what was copied or cut in here, newest first
+    ring_paste: Option<RingPaste>, // the last paste from the clipboard history, to cycle on
+}
+
+/// input of the go-to-line bar
+#[derive(Debug, Clone, Default)]
+struct GoToLine {
+    input: String,
+    error: Option<String>,
 }
 
 #[derive(Debug, Clone)]
 enum Message {
     Edit(text_editor::Action),
     Open,
-    FileOpened(Result<(PathBuf, Arc<String>), Error>),
+    FileOpened(usize, Result<LoadedFile, Error>), // generation of the request
+    FileOpenedAt(Result<LoadedFile, Error>, (usize, usize)),
+    LightAnswered(LoadedFile, Option<(usize, usize)>, bool), // whether to open it read-only
+    FilesOpened(usize, Result<Vec<OpenedFile>, Error>), // generation of the request
+    MoveCursor((usize, usize)),
     New,
+    NewScratch,
+    Save,
+    SaveAs,
+    FileSaved(usize, usize, Result<PathBuf, Error>), // buffer id and generation
+    Undo,
+    Redo,
+    Unsaved(Pending, Choice),
+    SavedBefore(Pending, Result<PathBuf, Error>),
+    ZoomIn,
+    ZoomOut,
+    ResetZoom,
+    ToggleTheme,
+    SettingsSaved(Result<(), Error>),
+    ToggleSearch,
+    SearchChanged(String),
+    ReplacementChanged(String),
+    CaseSensitiveToggled(bool),
+    RegexToggled(bool),
+    Find(String),
+    Replace { find: String, replace: String },
+    LineEndingSelected(LineEnding),
+    SaveWithEncoding(Encoding),
+    OpenRecent(PathBuf),
+    RecentOpened(PathBuf, Result<LoadedFile, Error>),
+    ReopenClosed,
+    ClosedReopened(PathBuf, (usize, usize), Result<LoadedFile, Error>),
+    ToggleLineNumbers,
+    AutoSave,
+    MirrorRecovery,
+    RecoveryUpdated(Result<(), Error>), // a copy written or removed
+    RecoveryFound(Vec<recovery::Recovered>),
+    RestoreAnswered(Vec<recovery::Recovered>, bool),
+    RecoveryOpened(recovery::Recovered, Result<LoadedFile, Error>),
+    FileDropped(PathBuf),
+    SelectTab(usize),
+    CloseTab(usize),
+    SetLanguage(Language),
+    SetSyntaxTheme(highlight::SyntaxTheme),
+    PinFile,
+    UnpinFile(PathBuf),
+    MovePinLeft(PathBuf),
+    OpenPinned(PathBuf),
+    ToggleGoToLine,
+    GoToLineChanged(String),
+    GoToLineSubmitted,
+    GoToLine(usize),
+    ToggleReadOnly,
+    Reload,
+    ReloadConfirmed(usize, bool),
+    Revert,
+    RevertConfirmed(usize, bool),
+    Reloaded(usize, Result<LoadedFile, Error>),
+    FileChanged(PathBuf),
+    ChangedOnDisk(usize, Result<LoadedFile, Error>),
+    WindowFocused,
+    CheckedOnDisk(usize, Option<Result<LoadedFile, Error>>),
+    ReloadAnswered(usize, LoadedFile, bool),
+    ToggleWrap,
+    Tick(Instant),
+    TogglePalette,
+    PaletteChanged(String),
+    PaletteSubmitted,
+    RunCommand(Box<Message>),
+    ToggleSplit,
+    DuplicateLine,
+    AddNextOccurrence,
+    SelectLine,
+    JumpToBracket,
+    SelectAll,
+    MoveLineUp,
+    MoveLineDown,
+    ModifiersChanged(keyboard::Modifiers),
+    Copied,
+    ClipboardRead(Option<String>),
+    PasteFromHistory,
+    ClearClipboardHistory,
+    ShowEffectiveSettings,
+    ExtendBlock { down: bool },
+    ToggleScrollPastEnd,
+    KeyPressed(keyboard::KeyCode, keyboard::Modifiers),
+    RefreshStats(Instant),
+    NewWindow,
+    RevealInFileManager,
+    CopyPath { relative: bool }, // to the working directory
+    Revealed(Result<(), Error>),
+    CloseRequested,
+    ToggleComment,
+    InsertDateTime,
+    ToggleMinimap,
+    ToggleIndentGuides,
+    ToggleRulers,
+    ToggleZen,
+    MinimapJump(usize),
+    WindowResized(u32),
+    FontChecked(String, bool),
+    ToggleShowWhitespace,
+    UpperCase,
+    SortLines { ascending: bool, case_insensitive: bool, unique: bool },
+    LowerCase,
+    TitleCase,
+    IncrementNumbers(i64),
+    CropToSelection,
+    TogglePreview,
+    PreviewRendered((usize, usize), Vec<preview::Block>),
+    Validated((usize, usize), Option<validate::Problem>), // buffer id and revision that was checked
+    SessionRestored(Vec<Result<LoadedFile, Error>>, usize),
+    StdinRead(Result<(Arc<String>, LineEnding, Encoding), Error>),
+    Exit,
+    OpenFolder,
+    OpenProject, // the active file's project, in the sidebar
+    FolderPicked(Result<PathBuf, Error>),
+    DirectoryRead(PathBuf, Result<Vec<tree::Node>, Error>),
+    ToggleDirectory(PathBuf),
+    OpenTreeFile(PathBuf),
+    FindInFilesChanged(String),
+    FindInFiles(String),
+    FoundInFiles(String, find_in_files::Found),
+    OpenHit(PathBuf, (usize, usize)), // 1-based line and column
+    CloseFindInFiles,
+    RunExternal,
+    Formatted(usize, usize, Result<String, String>), // buffer id and the revision that was formatted
+    ExternalFinished(usize, Result<external::Output, Error>),
+    CloseExternalOutput,
+    ScriptsListed(Vec<String>),
+    RunScript(String),
+    ScriptFinished(usize, usize, bool, Result<String, String>), // buffer id, revision and whether it ran on a selection
+    ShowDiff,
+    DiskVersionRead(usize, Result<LoadedFile, Error>),
+    CloseDiff,
+    DeleteLine,
+}
+
+/// a file read from disk
+#[derive(Debug, Clone)]
+struct LoadedFile {
+    path: PathBuf,
+    content: Arc<String>,
+    line_ending: LineEnding,
+    encoding: Encoding,
+    read_only: bool, // we lack write permission
+    modified: Option<SystemTime>,
+    project: PathBuf, // see `tree::project_root`
+}
+
+/// one of several files opened together, with where its cursor goes, 1-based
+#[derive(Debug, Clone)]
+struct OpenedFile {
+    path: PathBuf,
+    position: Option<(usize, usize)>,
+    file: Result<LoadedFile, Error>,
+}
+
+/// where a paste from the clipboard history went, so the next one can replace it
+#[derive(Debug, Clone, Copy)]
+struct RingPaste {
+    id: usize,
+    revision: usize, // of the buffer right after the paste
+    index: usize, // into the history
+    start: (usize, usize),
+    end: (usize, usize),
+}
+
+/// line terminator used when writing the file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+enum LineEnding {
+    #[default]
+    Lf,
+    CrLf,
+}
+
+impl LineEnding {
+    const ALL: [LineEnding; 2] = [LineEnding::Lf, LineEnding::CrLf];
+
+    /// the ending used by most lines of `text`
+    fn detect(text: &str) -> Self {
+        let crlf = text.matches("\r\n").count();
+        let lf = text.matches('\n').count() - crlf;
+
+        if crlf > lf { LineEnding::CrLf } else { LineEnding::Lf }
+    }
+
+    /// rewrite the editor's `\n` lines with this ending
+    fn apply(self, text: &str) -> String {
+        match self {
+            LineEnding::Lf => text.to_owned(),
+            LineEnding::CrLf => text.replace('\n', "\r\n"),
+        }
+    }
+}
+
+impl fmt::Display for LineEnding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            LineEnding::Lf => "LF",
+            LineEnding::CrLf => "CRLF",
+        })
+    }
+}
+
+/// operation waiting on the unsaved-changes prompt, or on a save
+#[derive(Debug, Clone, Copy)]
+enum Pending {
+    Close(usize), // buffer id
+    Quit(usize),  // the dirty buffer being asked about, the rest come after it
+    Run(usize),   // the external command, once the buffer is saved
+}
+
+impl Pending {
+    /// id of the buffer whose changes are at stake
+    fn buffer(self) -> usize {
+        match self {
+            Pending::Close(id) | Pending::Quit(id) | Pending::Run(id) => id,
+        }
+    }
+}
+
+/// answer to the unsaved-changes prompt
+#[derive(Debug, Clone, Copy)]
+enum Choice {
     Save,
-    FileSaved(Result<(), Error>),
+    Discard,
+    Cancel,
 }
 
 impl Application for Editor {
@@ -44,148 +350,3145 @@ impl Application for Editor {
     type Flags = ();
 
     fn new(_flags: Self::Flags) -> (Self, Command<Message>) { // app initial state
+        let mut settings = settings::Settings::load();
+
+        // the last file may have been moved or deleted since, which is no error
+        // worth showing at startup; forget it and start on an empty buffer
+        let forgotten = settings.last_open_path.as_ref().is_some_and(|path| !path.is_file());
+        if forgotten {
+            settings.last_open_path = None;
+        }
+
+        // open the file given on the command line, else the last one we had,
+        // else start on an empty buffer
+        let stdin = std::env::args().nth(1).is_some_and(|arg| arg == STDIN_ARG);
+        let session = match std::env::args().nth(1) {
+            None if settings.restore_session => session::Session::load().filter(|session| !session.paths.is_empty()),
+            _ => None,
+        };
+        // several paths, as a shell glob gives, each get a tab
+        let many: Vec<_> = std::env::args()
+            .skip(1)
+            .filter(|arg| arg != NEW_WINDOW_FLAG && arg != STDIN_ARG)
+            .map(|arg| parse_location(&arg))
+            .collect();
+        let (path, position) = match std::env::args().nth(1) {
+            _ if many.len() > 1 => (None, None),
+            Some(arg) if arg == NEW_WINDOW_FLAG || arg == STDIN_ARG => (None, None),
+            Some(arg) => {
+                let (path, position) = parse_location(&arg);
+                (Some(path), position)
+            },
+            None if session.is_some() => (None, None),
+            None => (settings.last_open_path.clone(), None),
+        };
+
+        // a file named on the command line is the first thing being opened
+        let opening = (path.is_some() && position.is_none()) || many.len() > 1;
+        let command = match (path, position) {
+            (None, _) if many.len() > 1 => Command::perform(
+                load_files(many, settings.max_file_size()),
+                |files| Message::FilesOpened(0, Ok(files)),
+            ),
+            (Some(path), Some(position)) => Command::perform(
+                load_file(path, settings.max_file_size()),
+                move |result| Message::FileOpenedAt(result, position),
+            ),
+            (Some(path), None) => Command::perform(
+                load_file(path, settings.max_file_size()),
+                |result| Message::FileOpened(0, result),
+            ),
+            // read in the background, a slow pipe shouldn't freeze the window
+            (None, _) if stdin => Command::perform(read_stdin(settings.max_file_size()), Message::StdinRead),
+            (None, _) => match session {
+                Some(session) => Command::perform(
+                    session.restore(settings.max_file_size()),
+                    |(files, active)| Message::SessionRestored(files, active),
+                ),
+                None => Command::none(),
+            },
+        };
+
+        // only to warn about a missing font, iced falls back on its own
+        let font = fonts::editor_font(settings.font.as_deref());
+        let command = match settings.font.clone() {
+            Some(family) => Command::batch([
+                command,
+                Command::perform(fonts::is_installed(family.clone()), move |found| Message::FontChecked(family, found)),
+            ]),
+            None => command,
+        };
+
+        // copies left behind by a crash, whether or not mirroring is still on
+        let command = Command::batch([command, Command::perform(recovery::find(), Message::RecoveryFound)]);
+        let command = match forgotten {
+            true => Command::batch([command, Command::perform(settings.clone().save(), Message::SettingsSaved)]),
+            false => command,
+        };
+
+        let keymap = keymap::Keymap::new(&settings.keybindings);
+        let notice = (!keymap.warnings.is_empty()).then(|| {
+            (format!("Ignored keybindings: {}", keymap.warnings.join("; ")), Instant::now() + WARNING_DURATION)
+        });
+
         (Self {
-            path: None,
-            content: text_editor::Content::new(),
-            error: None,
+            buffers: vec![Buffer::new(0)],
+            active: 0,
+            next_id: 1,
+            dialog_open: false,
+            modifiers: keyboard::Modifiers::default(),
+            settings,
+            font,
+            keymap,
+            search: search::Search::default(),
+            go_to_line: None,
+            palette: None,
+            preview: None,
+            validation: validate::Validation::default(),
+            find_in_files: find_in_files::FindInFiles::default(),
+            external_output: None,
+            diff: None,
+            generation: 0,
+            opening: opening.then_some(0),
+            mirrored: HashMap::new(),
+            closed: Vec::new(),
+            split: false,
+            zen: false,
+            tree: None,
+            window_height: 768.0, // iced's default window size
+            notice,
+            clipboard: Vec::new(),
+            ring_paste: None,
         },
-            Command::perform(
-                load_file(default_file()),
-          Message::FileOpened,
-            ),
+            command,
         )
     }
 
     fn title(&self) -> String { // title of window app
-        String::from("Crab!")
+        let buffer = self.buffer();
+        let path = buffer.path.as_deref();
+        let filename = match (buffer.scratch, path.and_then(Path::file_name)) {
+            (true, _) => "Scratch".into(),
+            (false, Some(name)) => name.to_string_lossy(),
+            (false, None) => "Untitled".into(),
+        };
+        let dir = path.and_then(Path::parent).map(|dir| dir.display().to_string()).unwrap_or_default();
+
+        // one pass, so a file name containing "{app}" isn't substituted again
+        let mut title = String::new();
+        let mut rest = self.settings.title_format.as_str();
+
+        while let Some(start) = rest.find('{') {
+            title.push_str(&rest[..start]);
+            rest = &rest[start..];
+
+            let (placeholder, value) = [
+                ("{dirty}", if buffer.is_dirty { "* " } else { "" }),
+                ("{filename}", &*filename),
+                ("{dir}", &*dir),
+                ("{app}", "Crab!"),
+            ]
+            .into_iter()
+            .find(|(placeholder, _)| rest.starts_with(placeholder))
+            .unwrap_or(("{", "{")); // not ours, keep the brace
+
+            title.push_str(value);
+            rest = &rest[placeholder.len()..];
+        }
+
+        title + rest
     }
 
     fn update(&mut self, message: Message) -> Command<Message> {
-        match message {
+        let command = match message {
+            // the editor pastes on ctrl+shift+v as well, that one comes from the history
+            Message::Edit(text_editor::Action::Edit(text_editor::Edit::Paste(_)))
+                if self.modifiers.command() && self.modifiers.shift() =>
+            {
+                self.update(Message::PasteFromHistory)
+            },
+            // every cursor gets the plain edit, indenting and pairing only know the editor's own
+            Message::Edit(text_editor::Action::Edit(edit)) if self.buffer().has_extra_cursors() => {
+                let buffer = self.buffer_mut();
+                buffer.edit_all(edit);
+                buffer.error = None;
+                Command::none()
+            },
+            // ctrl+click leaves a cursor behind before moving the editor's
+            Message::Edit(text_editor::Action::Click(point)) if self.modifiers.command() => {
+                let buffer = self.buffer_mut();
+                buffer.add_cursor();
+                buffer.perform(text_editor::Action::Click(point));
+                Command::none()
+            },
+            // alt+drag selects a rectangle, each line of it a cursor of its own
+            Message::Edit(text_editor::Action::Click(point)) if self.modifiers.alt() => {
+                let buffer = self.buffer_mut();
+                buffer.clear_cursors();
+                buffer.perform(text_editor::Action::Click(point));
+                buffer.start_block();
+                Command::none()
+            },
+            Message::Edit(text_editor::Action::Drag(point)) if self.modifiers.alt() => {
+                let buffer = self.buffer_mut();
+                buffer.perform(text_editor::Action::Drag(point));
+                buffer.drag_block();
+                Command::none()
+            },
+            Message::Edit(text_editor::Action::Select(text_editor::Motion::Up)) if self.modifiers.alt() => {
+                self.update(Message::ExtendBlock { down: false })
+            },
+            Message::Edit(text_editor::Action::Select(text_editor::Motion::Down)) if self.modifiers.alt() => {
+                self.update(Message::ExtendBlock { down: true })
+            },
+            Message::Edit(text_editor::Action::Edit(text_editor::Edit::Insert('\t'))) if self.effective().use_spaces => {
+                // pad to the next tab stop instead of inserting a tab
+                let tab_width = self.effective().tab_width;
+                let buffer = self.buffer_mut();
+                let spaces = tab_width - buffer.visual_column(tab_width) % tab_width;
+
+                buffer.perform(text_editor::Action::Edit(text_editor::Edit::Paste(Arc::new(" ".repeat(spaces)))));
+                buffer.error = None;
+                Command::none()
+            }
+            Message::Edit(text_editor::Action::Edit(text_editor::Edit::Insert(c)))
+                if self.settings.wrap_selection && "([{\"'`".contains(c) && self.buffer().content.selection().is_some() =>
+            {
+                let buffer = self.buffer_mut();
+                buffer.wrap_selection(c);
+                buffer.error = None;
+                Command::none()
+            },
+            Message::Edit(text_editor::Action::Edit(text_editor::Edit::Insert(c))) if self.settings.auto_close && "()[]{}\"'`".contains(c) => {
+                let buffer = self.buffer_mut();
+                buffer.insert_paired(c);
+                buffer.error = None;
+                Command::none()
+            },
+            Message::Edit(text_editor::Action::Edit(text_editor::Edit::Backspace)) if self.settings.auto_close => {
+                let buffer = self.buffer_mut();
+                buffer.backspace_paired();
+                buffer.error = None;
+                Command::none()
+            },
+            Message::Edit(text_editor::Action::Edit(text_editor::Edit::Enter)) if self.effective().auto_indent => {
+                let effective = self.effective();
+                let unit = match effective.use_spaces {
+                    true => " ".repeat(effective.tab_width),
+                    false => String::from("\t"),
+                };
+                let buffer = self.buffer_mut();
+                let code = !matches!(buffer.language(), Language::PlainText | Language::Markdown);
+
+                buffer.indented_newline(&unit, code);
+                buffer.error = None;
+                Command::none()
+            },
+            Message::Edit(text_editor::Action::Move(text_editor::Motion::Home)) if self.settings.smart_home => {
+                let buffer = self.buffer_mut();
+                buffer.clear_cursors();
+                buffer.smart_home(false);
+                Command::none()
+            },
+            Message::Edit(text_editor::Action::Select(text_editor::Motion::Home)) if self.settings.smart_home => {
+                let buffer = self.buffer_mut();
+                buffer.clear_cursors();
+                buffer.smart_home(true);
+                Command::none()
+            },
+            // the editor takes arrow keys itself, so alt+arrow arrives as a cursor move
+            Message::Edit(text_editor::Action::Move(text_editor::Motion::Up)) if self.modifiers.alt() => {
+                self.update(Message::MoveLineUp)
+            },
+            Message::Edit(text_editor::Action::Move(text_editor::Motion::Down)) if self.modifiers.alt() => {
+                self.update(Message::MoveLineDown)
+            },
+            // ctrl+arrow likewise, it only changes numbers when the selection has some
+            // and moves the cursor as usual otherwise
+            Message::Edit(text_editor::Action::Move(text_editor::Motion::Up))
+                if self.modifiers.command() && self.changes_numbers(1) =>
+            {
+                self.update(Message::IncrementNumbers(1))
+            },
+            Message::Edit(text_editor::Action::Move(text_editor::Motion::Down))
+                if self.modifiers.command() && self.changes_numbers(-1) =>
+            {
+                self.update(Message::IncrementNumbers(-1))
+            },
             Message::Edit(action) => {
-                self.content.edit(action);
-                self.error = None; // clear error when re-editing
+                let buffer = self.buffer_mut();
+
+                // moving on drops the extra cursors, scrolling and dragging don't
+                if matches!(action, text_editor::Action::Move(_) | text_editor::Action::Select(_)
+                    | text_editor::Action::Click(_) | text_editor::Action::SelectWord | text_editor::Action::SelectLine)
+                {
+                    buffer.clear_cursors();
+                }
+
+                buffer.perform(action);
+                buffer.error = None; // clear error when re-editing
                 Command::none()
             }
-            Message::Open => Command::perform(pick_file(), Message::FileOpened),
-            Message::FileOpened(Ok((path, content))) => {
-                self.path = Some(path);
-                self.content = text_editor::Content::with(&content);
+            Message::Open => self.open(),
+            // overtaken by a newer open, or by New
+            Message::FileOpened(generation, _) if self.opening != Some(generation) => Command::none(),
+            Message::FileOpened(_, Ok(file)) => {
+                self.opening = None;
+
+                // pick up where we left off, unless the file is already open
+                let is_open = self.buffers.iter().any(|buffer| buffer.path.as_ref() == Some(&file.path));
+                let position = match is_open {
+                    true => None,
+                    false => self.settings.cursors.get(&file.path).map(|&(line, column)| (line + 1, column + 1)),
+                };
+
+                self.opened(file, position)
+            },
+            Message::FilesOpened(generation, _) if self.opening != Some(generation) => Command::none(),
+            Message::FilesOpened(generation, Err(error)) => self.update(Message::FileOpened(generation, Err(error))),
+            // a single file is an ordinary open, errors and all
+            Message::FilesOpened(generation, Ok(mut files)) if files.len() == 1 && files[0].position.is_none() => {
+                self.update(Message::FileOpened(generation, files.remove(0).file))
+            },
+            Message::FilesOpened(_, Ok(files)) => {
+                self.opening = None;
+                self.dialog_open = false;
+                let mut failed = Vec::new();
+
+                for OpenedFile { path, position, file } in files {
+                    let file = match file {
+                        Ok(file) => file,
+                        // the others still open, this one is only reported
+                        Err(error) => {
+                            failed.push(format!("{}: {error}", path.display()));
+                            continue;
+                        },
+                    };
+
+                    let is_open = self.buffers.iter().any(|buffer| buffer.path.as_ref() == Some(&file.path));
+                    let cursor = match position {
+                        Some((line, column)) => Some((line.saturating_sub(1), column.saturating_sub(1))),
+                        None if is_open => None,
+                        None => self.settings.cursors.get(&file.path).copied(),
+                    };
+
+                    self.open_buffer(file, false);
+                    self.remember_recent();
+                    if let Some(cursor) = cursor {
+                        self.buffer_mut().move_to_clamped(cursor);
+                    }
+                }
+
+                self.settings.last_open_path = self.buffer().path.clone();
+                if !failed.is_empty() {
+                    self.notice = Some((format!("Couldn't open {}", failed.join("; ")), Instant::now() + WARNING_DURATION));
+                }
+
+                self.save_settings()
+            },
+            Message::FileOpenedAt(Ok(file), position) => self.opened(file, Some(position)),
+            Message::FileOpenedAt(Err(error), _) => {
+                let generation = self.start_opening();
+                self.update(Message::FileOpened(generation, Err(error)))
+            },
+            Message::LightAnswered(file, position, light) => self.show(file, position, light),
+            Message::MoveCursor((line, column)) => {
+                // 1-based like compiler output, clamped to the text
+                self.buffer_mut().move_to_clamped((line.saturating_sub(1), column.saturating_sub(1)));
 
                 Command::none()
             },
             Message::New => {
-                self.path = None;
-                self.content = text_editor::Content::new();
+                self.cancel_opening();
+                let buffer = Buffer::new(self.next_id());
+                self.buffers.push(buffer);
+                self.active = self.buffers.len() - 1;
 
                 Command::none()
             },
-            Message::Save => {
-                let text = self.content.text();
-                let path = self.path.clone();
-                Command::perform(save_file(path, text), Message::FileSaved)
+            Message::NewScratch => {
+                self.cancel_opening();
+                let mut buffer = Buffer::new(self.next_id());
+                buffer.scratch = true;
+                self.buffers.push(buffer);
+                self.active = self.buffers.len() - 1;
+
+                Command::none()
             },
-            Message::FileSaved(Ok(())) => {
-                self.error = None;
+            Message::SelectTab(index) => {
+                if index < self.buffers.len() {
+                    self.active = index;
+                }
 
                 Command::none()
             },
-            Message::FileSaved(Err(error)) => {
-                self.error = Some(error);
+            Message::CloseTab(index) => match self.buffers.get(index).map(|buffer| (buffer.id, buffer.is_dirty)) {
+                Some((id, true)) => self.confirm_discard(Pending::Close(id)),
+                Some((id, false)) => self.close(id),
+                None => Command::none(),
+            },
+            Message::Unsaved(pending, Choice::Save) => {
+                let backup = self.settings.backup_suffix.clone();
+
+                match self.prepare_save(pending.buffer()) {
+                    Some(buffer) => Command::perform(buffer.save(backup), move |result| Message::SavedBefore(pending, result)),
+                    None => self.proceed(pending),
+                }
+            },
+            Message::Unsaved(pending, Choice::Discard) => self.proceed(pending),
+            Message::Unsaved(_, Choice::Cancel) => {
+                self.dialog_open = false;
 
                 Command::none()
             },
-            Message::FileOpened(Err(error)) => {
-                self.error = Some(error);
-        
+            Message::SavedBefore(pending, Ok(path)) => {
+                if let Some(buffer) = self.find_mut(pending.buffer()) {
+                    buffer.path = Some(path);
+                    buffer.mark_saved();
+                }
+                self.proceed(pending)
+            },
+            Message::SavedBefore(_, Err(Error::DialogClosed)) => {
+                // cancelled the save, so cancel what it was saving for too
+                self.dialog_open = false;
+
                 Command::none()
-            }
-        }
-    }
+            },
+            Message::SavedBefore(pending, Err(Error::IsDirectory(path))) => {
+                let backup = self.settings.backup_suffix.clone();
 
-    fn view(&self) -> Element<'_, Message> {
-        let controls = row![
-            button("New").on_press(Message::New),
-            button("Open").on_press(Message::Open),
-            button("Save").on_press(Message::Save)]
-            .spacing(5);
-        
-        let input = text_editor(&self.content).on_edit(Message::Edit);
+                match self.find_mut(pending.buffer()) {
+                    Some(buffer) => {
+                        buffer.error = Some(Error::IsDirectory(path));
+                        Command::perform(buffer.save_as(backup), move |result| Message::SavedBefore(pending, result))
+                    },
+                    None => self.proceed(pending),
+                }
+            },
+            Message::SavedBefore(pending, Err(error)) => {
+                // keep the buffer so nothing is lost
+                self.dialog_open = false;
+                if let Some(buffer) = self.find_mut(pending.buffer()) {
+                    buffer.error = Some(error);
+                }
 
-        
-        let status_bar = {
-            let status = if let Some(Error::IO(error)) = self.error.as_ref() {
-                text(error.to_string())
-                } else {
-                match self.path.as_deref().and_then(Path::to_str) {
-                Some(path) => text(path).size(14),
-                None => text("New File"),
+                Command::none()
+            },
+            Message::ZoomIn => {
+                self.settings.font_size = (self.settings.font_size + ZOOM_STEP).min(MAX_FONT_SIZE);
+
+                self.save_settings()
+            },
+            Message::ZoomOut => {
+                self.settings.font_size = (self.settings.font_size - ZOOM_STEP).max(MIN_FONT_SIZE);
+
+                self.save_settings()
+            },
+            Message::ResetZoom => {
+                self.settings.font_size = DEFAULT_FONT_SIZE;
+
+                self.save_settings()
+            },
+            Message::ToggleTheme => {
+                self.settings.theme = self.settings.theme.toggled();
+
+                self.save_settings()
+            },
+            Message::SettingsSaved(Ok(())) => Command::none(),
+            Message::SettingsSaved(Err(error)) => {
+                self.buffer_mut().error = Some(error);
+
+                Command::none()
+            },
+            Message::ToggleSearch => {
+                self.search.visible = !self.search.visible;
+
+                Command::none()
+            },
+            Message::SearchChanged(query) => {
+                self.search.query = query;
+
+                Command::none()
+            },
+            Message::ReplacementChanged(replacement) => {
+                self.search.replacement = replacement;
+
+                Command::none()
+            },
+            Message::CaseSensitiveToggled(case_sensitive) => {
+                self.search.case_sensitive = case_sensitive;
+
+                Command::none()
+            },
+            Message::RegexToggled(regex) => {
+                self.search.regex = regex;
+
+                Command::none()
+            },
+            Message::Find(query) => {
+                // search from the cursor, so a selected match is skipped
+                let backwards = self.modifiers.shift();
+                let buffer = &mut self.buffers[self.active];
+                let text = buffer.content.text();
+                let from = search::offset(&text, buffer.content.cursor_position());
+
+                self.search.query = query;
+                self.search.refresh(buffer.id, buffer.revision(), &text);
+
+                if let Some(found) = self.search.step(from, backwards) {
+                    buffer.select(search::position(&text, found.start), search::position(&text, found.end));
                 }
-            };
-            
-            let position = {
-                let (line, column) = self.content.cursor_position();
-                
-                text(format!("{}:{}", line + 1, column + 1))
-            };
-            row![status, horizontal_space(Length::Fill), position]
-        };
-        container(column![controls, input, status_bar].spacing(5)).padding(5).into()
-    }
 
-    fn theme(&self) -> Theme {
-        Theme::Dark
-    }
-    
-}
+                Command::none()
+            },
+            Message::OpenRecent(path) => self.open_recent(path),
+            Message::RecentOpened(_, Ok(file)) => {
+                let generation = self.start_opening();
+                self.update(Message::FileOpened(generation, Ok(file)))
+            },
+            Message::RecentOpened(path, Err(error)) => {
+                // the file is gone or unreadable, so stop offering it
+                self.settings.recent.retain(|recent| *recent != path);
+                self.buffer_mut().error = Some(error);
 
-/// set default file
-fn default_file() -> PathBuf {
-    PathBuf::from(format!("{}/src/main.rs", env!("CARGO_MANIFEST_DIR")))
-}
+                self.save_settings()
+            },
+            Message::ReopenClosed => match self.closed.pop() {
+                Some((path, position)) => Command::perform(
+                    load_file(path.clone(), self.settings.max_file_size()),
+                    move |result| Message::ClosedReopened(path, position, result),
+                ),
+                None => {
+                    self.notify(String::from("No closed tabs to reopen"));
+                    Command::none()
+                },
+            },
+            Message::ClosedReopened(_, (line, column), Ok(file)) => self.opened(file, Some((line + 1, column + 1))),
+            // gone or unreadable since, try the one closed before it
+            Message::ClosedReopened(path, _, Err(error)) => {
+                let command = self.update(Message::ReopenClosed);
+                self.notice = Some((format!("Skipped {}: {error}", path.display()), Instant::now() + WARNING_DURATION));
 
+                command
+            },
+            // only files that already have a path, so we never pop the save dialog
+            Message::AutoSave => {
+                let saved = |buffer: &Buffer| buffer.is_dirty && buffer.path.is_some();
 
-/// pick a file
-async fn pick_file() -> Result<(PathBuf, Arc<String>), Error> {
-    let handle = rfd::AsyncFileDialog::new()
-        .set_title("Choose a text file...")
-        .pick_file()
-        .await
-        .ok_or(Error::DialogClosed)?;
-    
-    load_file(handle.path().to_owned()).await
-}
- 
-/// file loader
-async fn load_file(path: PathBuf) -> Result<(PathBuf, Arc<String>), Error> {
-    let content = tokio::fs::read_to_string(&path)
-        .await
-        .map(Arc::new)
-        .map_err(|error| error.kind())
-        .map_err(Error::IO)?;
+                // the newline goes in the buffer too, so it matches what's on disk
+                let options = editor_core::SaveOptions { final_newline: true, ..Default::default() };
 
-    Ok((path, content))
-}
+                for buffer in self.buffers.iter_mut().filter(|buffer| saved(buffer)) {
+                    if self.settings.effective(buffer.path.as_deref()).final_newline {
+                        buffer.apply(buffer.document().prepared(options));
+                    }
+                }
 
-/// file saver
-async fn save_file(path: Option<PathBuf>, text: String) -> Result<(), Error> {
-    // if we have a path we save to it, else we ask for a new path
-    let path = if let Some(path) = path { path } else {
-        rfd::AsyncFileDialog::new()
-            .set_title("Choose a file name...")
-            .save_file()
-            .await
-            .ok_or(Error::DialogClosed)
-            .map(|handle| handle.path().to_owned())?
-    };
+                let ids: Vec<_> = self.buffers.iter().filter(|buffer| saved(buffer)).map(|buffer| buffer.id).collect();
 
-    tokio::fs::write(&path, &text)
-        .await
-        .map_err(|error| Error::IO(error.kind()))
-}
+                Command::batch(ids.into_iter().map(|id| {
+                    let generation = self.next_generation();
+                    let backup = self.settings.backup_suffix.clone();
+                    let Some(buffer) = self.find_mut(id) else {
+                        return Command::none();
+                    };
 
-#[derive(Debug, Clone)]
-enum Error {
-    DialogClosed,
-    IO(io::ErrorKind)
+                    buffer.saving = Some(generation);
+                    Command::perform(buffer.save(backup), move |result| Message::FileSaved(id, generation, result))
+                }))
+            },
+            Message::MirrorRecovery => {
+                let changed: Vec<_> = self
+                    .buffers
+                    .iter()
+                    .filter(|buffer| buffer.is_dirty && self.mirrored.get(&buffer.id) != Some(&buffer.revision()))
+                    .filter_map(|buffer| Some((buffer.id, buffer.revision(), buffer.path.clone()?, buffer.content.text())))
+                    .collect();
+
+                Command::batch(changed.into_iter().map(|(id, revision, path, text)| {
+                    self.mirrored.insert(id, revision);
+                    Command::perform(recovery::write(path, text), Message::RecoveryUpdated)
+                }))
+            },
+            Message::RecoveryUpdated(Ok(())) => Command::none(),
+            Message::RecoveryUpdated(Err(error)) => {
+                self.notice = Some((error.to_string(), Instant::now() + WARNING_DURATION));
+
+                Command::none()
+            },
+            Message::RecoveryFound(found) if found.is_empty() => Command::none(),
+            Message::RecoveryFound(found) => {
+                let names: Vec<_> = found.iter().map(|recovered| recovered.path.display().to_string()).collect();
+
+                self.dialog_open = true;
+                Command::perform(ask_restore(names), move |yes| Message::RestoreAnswered(found, yes))
+            },
+            Message::RestoreAnswered(found, yes) => {
+                self.dialog_open = false;
+                let max_size = self.settings.max_file_size();
+
+                Command::batch(found.into_iter().map(|recovered| match yes {
+                    // the file is opened as usual, then the recovered text goes on top as an edit
+                    true => Command::perform(load_file(recovered.path.clone(), max_size), move |file| {
+                        Message::RecoveryOpened(recovered, file)
+                    }),
+                    false => Command::perform(recovery::remove(recovered.path), Message::RecoveryUpdated),
+                }))
+            },
+            Message::RecoveryOpened(recovered, file) => {
+                let recovery::Recovered { path, text } = recovered;
+
+                match file {
+                    Ok(file) => self.open_buffer(file, false),
+                    // gone since, saving puts it back
+                    Err(_) => {
+                        let mut buffer = Buffer::new(self.next_id());
+                        buffer.path = Some(path.clone());
+                        self.buffers.push(buffer);
+                        self.active = self.buffers.len() - 1;
+                    },
+                }
+
+                let buffer = self.buffer_mut();
+                if buffer.path.as_ref() == Some(&path) {
+                    buffer.apply(editor_core::Document { text, is_dirty: true, ..buffer.document() });
+                }
+
+                Command::none()
+            },
+            // a multi-file drop arrives as one event per file: keep the first
+            Message::FileDropped(_) if self.dialog_open => Command::none(),
+            Message::FileDropped(path) => {
+                self.dialog_open = true; // busy until FileOpened, so further drops are ignored
+                let generation = self.start_opening();
+                Command::perform(load_file(path, self.settings.max_file_size()), move |result| Message::FileOpened(generation, result))
+            },
+            // the std mappings are full unicode ones, so ß becomes SS
+            Message::UpperCase => {
+                self.buffer_mut().transform_selection(str::to_uppercase);
+
+                Command::none()
+            },
+            Message::SortLines { ascending, case_insensitive, unique } => {
+                self.buffer_mut().sort_lines(ascending, case_insensitive, unique);
+
+                Command::none()
+            },
+            Message::LowerCase => {
+                self.buffer_mut().transform_selection(str::to_lowercase);
+
+                Command::none()
+            },
+            // only ever on a selection, a number under a bare cursor is left alone
+            Message::IncrementNumbers(delta) => {
+                let buffer = self.buffer_mut();
+                if buffer.content.selection().is_some() {
+                    buffer.transform_selection(|text| increment_numbers(text, delta));
+                }
+
+                Command::none()
+            },
+            Message::TitleCase => {
+                self.buffer_mut().transform_selection(title_case);
+
+                Command::none()
+            },
+            Message::CropToSelection => {
+                self.buffer_mut().crop_to_selection();
+
+                Command::none()
+            },
+            Message::TogglePreview => {
+                if self.preview.take().is_none() && self.buffer().language() == Language::Markdown {
+                    self.preview = Some(preview::Preview::default());
+                }
+
+                Command::none()
+            },
+            Message::PreviewRendered(key, blocks) => {
+                if let Some(preview) = &mut self.preview {
+                    preview.blocks = blocks;
+                    preview.rendered = Some(key);
+                    preview.pending = None;
+                }
+
+                Command::none()
+            },
+            Message::Validated(key, problem) => {
+                self.validation = validate::Validation { problem, checked: Some(key), pending: None };
+
+                Command::none()
+            },
+            Message::ToggleShowWhitespace => {
+                self.settings.show_whitespace = !self.settings.show_whitespace;
+
+                self.save_settings()
+            },
+            Message::ToggleLineNumbers => {
+                self.settings.line_numbers = !self.settings.line_numbers;
+
+                self.save_settings()
+            },
+            Message::ToggleGoToLine => {
+                if self.go_to_line.take().is_some() {
+                    return Command::none();
+                }
+
+                self.go_to_line = Some(GoToLine::default());
+                text_input::focus(go_to_line_id())
+            },
+            Message::TogglePalette => {
+                if self.palette.take().is_some() {
+                    return Command::none();
+                }
+
+                self.palette = Some(palette::Palette::default());
+                Command::batch([
+                    text_input::focus(palette_id()),
+                    Command::perform(scripting::list(), Message::ScriptsListed),
+                ])
+            },
+            Message::ScriptsListed(scripts) => {
+                if let Some(palette) = &mut self.palette {
+                    palette.scripts = scripts;
+                }
+
+                Command::none()
+            },
+            Message::PaletteChanged(query) => {
+                if let Some(palette) = &mut self.palette {
+                    palette.query = query;
+                }
+
+                Command::none()
+            },
+            Message::PaletteSubmitted => {
+                // enter runs the best match
+                let best = self.palette.as_ref().and_then(|palette| {
+                    let command = palette::filter(&palette.query, self.buffer()).into_iter().next().map(|entry| entry.message);
+                    command.or_else(|| palette::filter_scripts(&palette.query, &palette.scripts).into_iter().next().map(|(_, message)| message))
+                });
+
+                match best {
+                    Some(message) => self.update(Message::RunCommand(Box::new(message))),
+                    None => Command::none(),
+                }
+            },
+            Message::RunCommand(message) => {
+                self.palette = None;
+                self.update(*message)
+            },
+            Message::GoToLineChanged(input) => {
+                if let Some(go_to_line) = &mut self.go_to_line {
+                    go_to_line.input = input;
+                    go_to_line.error = None;
+                }
+
+                Command::none()
+            },
+            Message::GoToLineSubmitted => {
+                let Some(go_to_line) = &mut self.go_to_line else {
+                    return Command::none();
+                };
+
+                match go_to_line.input.trim().parse::<usize>() {
+                    Ok(line) => self.update(Message::GoToLine(line)),
+                    Err(_) => {
+                        go_to_line.error = Some(format!("\"{}\" is not a line number", go_to_line.input.trim()));
+
+                        Command::none()
+                    },
+                }
+            },
+            Message::GoToLine(line) => {
+                // lines are 1-based here, out of range ones go to the last line
+                let buffer = self.buffer_mut();
+                let last = buffer.content.line_count().saturating_sub(1);
+
+                buffer.move_to((line.saturating_sub(1).min(last), 0));
+                self.go_to_line = None;
+
+                Command::none()
+            },
+            Message::Reload => {
+                let buffer = self.buffer();
+                let id = buffer.id;
+
+                match (buffer.path.is_some(), buffer.is_dirty) {
+                    (true, true) => {
+                        let ask = ask_reload(buffer.name().to_owned(), true, false);
+
+                        self.dialog_open = true;
+                        Command::perform(ask, move |yes| Message::ReloadConfirmed(id, yes))
+                    },
+                    (true, false) => self.reload(id),
+                    (false, _) => Command::none(),
+                }
+            },
+            Message::ReloadConfirmed(id, yes) => {
+                self.dialog_open = false;
+
+                if yes { self.reload(id) } else { Command::none() }
+            },
+            Message::Revert if !self.buffer().is_dirty => {
+                self.notify(String::from("No unsaved changes to revert"));
+
+                Command::none()
+            },
+            Message::Revert => {
+                let buffer = self.buffer();
+                let id = buffer.id;
+                let ask = ask_revert(buffer.name().to_owned(), buffer.path.is_some());
+
+                self.dialog_open = true;
+                Command::perform(ask, move |yes| Message::RevertConfirmed(id, yes))
+            },
+            Message::RevertConfirmed(id, yes) => {
+                self.dialog_open = false;
+                if !yes {
+                    return Command::none();
+                }
+
+                // the changes are thrown away on purpose, so is their recovery copy
+                self.mirrored.remove(&id);
+                match self.find(id).and_then(|buffer| buffer.path.clone()) {
+                    Some(path) => Command::batch([
+                        self.reload(id),
+                        Command::perform(recovery::remove(path), Message::RecoveryUpdated),
+                    ]),
+                    None => {
+                        if let Some(buffer) = self.find_mut(id) {
+                            buffer.clear();
+                        }
+
+                        Command::none()
+                    },
+                }
+            },
+            Message::Reloaded(id, result) => {
+                if let Some(buffer) = self.find_mut(id) {
+                    match result {
+                        Ok(file) => buffer.reload(file),
+                        Err(error) => buffer.error = Some(error),
+                    }
+                }
+
+                Command::none()
+            },
+            Message::FileChanged(_) if self.dialog_open => Command::none(),
+            Message::FileChanged(path) => {
+                match self.buffers.iter().find(|buffer| buffer.path.as_ref() == Some(&path)) {
+                    Some(buffer) => {
+                        let id = buffer.id;
+                        Command::perform(
+                            load_file(path, self.settings.max_file_size()),
+                            move |result| Message::ChangedOnDisk(id, result),
+                        )
+                    },
+                    None => Command::none(),
+                }
+            },
+            // the file was read back after a change event, ask before using it
+            Message::ChangedOnDisk(_, _) if self.dialog_open => Command::none(),
+            Message::ChangedOnDisk(id, Ok(file)) => match self.find(id) {
+                // our own saves trigger the watcher too
+                Some(buffer) if *file.content != buffer.content.text() => {
+                    let ask = ask_reload(buffer.name().to_owned(), buffer.is_dirty, true);
+
+                    self.dialog_open = true;
+                    Command::perform(ask, move |yes| Message::ReloadAnswered(id, file, yes))
+                },
+                _ => Command::none(),
+            },
+            Message::ChangedOnDisk(_, Err(_)) => Command::none(), // gone or mid-write, nothing to offer
+            Message::WindowFocused if !self.settings.reload_on_focus || self.dialog_open => Command::none(),
+            Message::WindowFocused => {
+                let max_size = self.settings.max_file_size();
+
+                Command::batch(self.buffers.iter().filter_map(|buffer| {
+                    let (id, path) = (buffer.id, buffer.path.clone()?);
+
+                    Some(Command::perform(
+                        load_if_modified(path, buffer.modified, max_size),
+                        move |file| Message::CheckedOnDisk(id, file),
+                    ))
+                }))
+            },
+            Message::CheckedOnDisk(id, Some(Ok(file))) => match self.find_mut(id) {
+                // only the time moved, most likely from our own save
+                Some(buffer) if *file.content == buffer.content.text() => {
+                    buffer.modified = file.modified;
+
+                    Command::none()
+                },
+                Some(buffer) if !buffer.is_dirty => {
+                    let name = buffer.name().to_owned();
+                    buffer.reload(file);
+                    self.notify(format!("Reloaded {name}, it changed on disk"));
+
+                    Command::none()
+                },
+                // one question at a time, the next focus asks about the rest
+                Some(buffer) if !self.dialog_open => {
+                    // asked once per change, saying no shouldn't bring it back on every focus
+                    buffer.modified = file.modified;
+                    let ask = ask_reload(buffer.name().to_owned(), true, true);
+
+                    self.dialog_open = true;
+                    Command::perform(ask, move |yes| Message::ReloadAnswered(id, file, yes))
+                },
+                _ => Command::none(),
+            },
+            Message::CheckedOnDisk(_, _) => Command::none(), // unchanged or gone
+            Message::ReloadAnswered(id, file, yes) => {
+                self.dialog_open = false;
+
+                if let Some(buffer) = self.find_mut(id).filter(|_| yes) {
+                    buffer.reload(file);
+                }
+
+                Command::none()
+            },
+            Message::Tick(now) => {
+                if self.notice.as_ref().is_some_and(|(_, until)| now >= *until) {
+                    self.notice = None;
+                }
+
+                Command::none()
+            },
+            Message::AddNextOccurrence => {
+                self.buffer_mut().add_next_occurrence();
+
+                Command::none()
+            },
+            Message::JumpToBracket => {
+                if !self.buffer_mut().jump_to_bracket() {
+                    self.notify(String::from("No matching bracket"));
+                }
+
+                Command::none()
+            },
+            Message::SelectLine => {
+                self.buffer_mut().select_line();
+
+                Command::none()
+            },
+            Message::SelectAll => {
+                self.buffer_mut().select_all();
+
+                Command::none()
+            },
+            Message::DuplicateLine => {
+                self.buffer_mut().duplicate_line();
+
+                Command::none()
+            },
+            Message::MoveLineUp => {
+                self.buffer_mut().move_line_up();
+
+                Command::none()
+            },
+            Message::MoveLineDown => {
+                self.buffer_mut().move_line_down();
+
+                Command::none()
+            },
+            Message::KeyPressed(key_code, modifiers) => match self.keymap.get(key_code, modifiers) {
+                Some(message) => self.update(message),
+                None => Command::none(),
+            },
+            // iced at this version has one window per application, so a new
+            // window is a new process with its own state
+            Message::NewWindow => {
+                let spawned = std::env::current_exe()
+                    .and_then(|exe| std::process::Command::new(exe).arg(NEW_WINDOW_FLAG).spawn());
+
+                if let Err(error) = spawned {
+                    self.buffer_mut().error = Some(Error::io("open a new window for", Path::new("Crab"))(error));
+                }
+
+                Command::none()
+            },
+            Message::ToggleMinimap => {
+                self.settings.minimap = !self.settings.minimap;
+
+                self.save_settings()
+            },
+            Message::ToggleZen => {
+                self.zen = !self.zen;
+
+                match self.settings.zen_maximize {
+                    true => window::maximize(self.zen),
+                    false => Command::none(),
+                }
+            },
+            Message::ToggleScrollPastEnd => {
+                self.settings.scroll_past_end = !self.settings.scroll_past_end;
+
+                self.save_settings()
+            },
+            Message::ToggleRulers => {
+                self.settings.show_rulers = !self.settings.show_rulers;
+
+                self.save_settings()
+            },
+            Message::ToggleIndentGuides => {
+                self.settings.indent_guides = !self.settings.indent_guides;
+
+                self.save_settings()
+            },
+            Message::SessionRestored(files, active) => {
+                let mut active_path = None;
+                let mut skipped = 0;
+
+                for (index, file) in files.into_iter().enumerate() {
+                    // a file that's gone since last time is skipped, not fatal
+                    let Ok(file) = file else {
+                        skipped += 1;
+                        continue;
+                    };
+
+                    if index == active {
+                        active_path = Some(file.path.clone());
+                    }
+
+                    let cursor = self.settings.cursors.get(&file.path).copied();
+                    self.open_buffer(file, false);
+
+                    if let Some(cursor) = cursor {
+                        self.buffer_mut().move_to_clamped(cursor);
+                    }
+                }
+
+                if let Some(index) = self.buffers.iter().position(|buffer| buffer.path.is_some() && buffer.path == active_path) {
+                    self.active = index;
+                }
+
+                if skipped > 0 {
+                    let notice = format!("Skipped {skipped} file(s) from the last session that couldn't be opened");
+                    self.notice = Some((notice, Instant::now() + WARNING_DURATION));
+                }
+
+                Command::none()
+            },
+            Message::FontChecked(family, false) => {
+                self.notice = Some((format!("Font \"{family}\" not found, using the default"), Instant::now() + WARNING_DURATION));
+
+                Command::none()
+            },
+            Message::FontChecked(_, true) => Command::none(),
+            Message::WindowResized(height) => {
+                self.window_height = height as f32;
+
+                Command::none()
+            },
+            Message::MinimapJump(line) => {
+                self.buffer_mut().move_to((line, 0));
+
+                Command::none()
+            },
+            Message::InsertDateTime => {
+                use std::fmt::Write;
+
+                // a bad format makes chrono's formatter error out, so fall back to ISO-8601
+                let now = chrono::Local::now();
+                let mut stamp = String::new();
+                if write!(stamp, "{}", now.format(&self.settings.date_format)).is_err() {
+                    stamp = now.format(settings::DEFAULT_DATE_FORMAT).to_string();
+                }
+
+                self.buffer_mut().perform(text_editor::Action::Edit(text_editor::Edit::Paste(Arc::new(stamp))));
+                Command::none()
+            },
+            Message::ToggleComment => {
+                let buffer = self.buffer_mut();
+
+                // files without a comment syntax are left alone
+                if let Some(comment) = comments::Comment::of(buffer.language(), buffer.path.as_deref()) {
+                    buffer.toggle_comment(comment);
+                }
+
+                Command::none()
+            },
+            Message::StdinRead(Ok((content, line_ending, encoding))) => {
+                // nothing piped in leaves the empty buffer we started with
+                if !content.is_empty() {
+                    self.buffers[self.active] = Buffer::from_text(self.next_id(), content, line_ending, encoding);
+                }
+
+                Command::none()
+            },
+            Message::StdinRead(Err(error)) => {
+                self.buffer_mut().error = Some(error);
+
+                Command::none()
+            },
+            Message::CloseRequested if self.dialog_open => Command::none(),
+            Message::CloseRequested => self.quit(),
+            Message::Exit => window::close(),
+            Message::RevealInFileManager => {
+                let Some(dir) = self.buffer().path.as_deref().and_then(Path::parent) else {
+                    return Command::none();
+                };
+
+                Command::perform(reveal(dir.to_owned()), Message::Revealed)
+            },
+            Message::CopyPath { relative } => {
+                let Some(path) = self.buffer().path.clone() else {
+                    return Command::none();
+                };
+
+                // a file outside the working directory keeps its absolute path
+                let path = match std::env::current_dir() {
+                    Ok(cwd) if relative => path.strip_prefix(&cwd).map(Path::to_owned).unwrap_or(path),
+                    _ => path,
+                };
+                let path = path.display().to_string();
+
+                self.notify(format!("Copied {path}"));
+                iced::clipboard::write(path)
+            },
+            Message::Revealed(Ok(())) => Command::none(),
+            Message::Revealed(Err(error)) => {
+                self.buffer_mut().error = Some(error);
+
+                Command::none()
+            },
+            Message::RefreshStats(now) => {
+                for buffer in &mut self.buffers {
+                    buffer.refresh_stats(now);
+                }
+
+                Command::none()
+            },
+            Message::OpenFolder => {
+                self.dialog_open = true;
+                Command::perform(tree::pick_folder(self.buffer().project.clone()), Message::FolderPicked)
+            },
+            Message::OpenProject => match self.buffer().project.clone() {
+                Some(project) => self.update(Message::FolderPicked(Ok(project))),
+                None => Command::none(),
+            },
+            Message::FolderPicked(Ok(path)) => {
+                self.dialog_open = false;
+                self.tree = Some(tree::Node { expanded: true, ..tree::Node::new(path.clone(), true) });
+
+                Command::perform(tree::read_dir(path.clone()), move |result| Message::DirectoryRead(path, result))
+            },
+            Message::FolderPicked(Err(Error::DialogClosed)) => {
+                self.dialog_open = false;
+
+                Command::none()
+            },
+            Message::FolderPicked(Err(error)) => {
+                self.dialog_open = false;
+                self.buffer_mut().error = Some(error);
+
+                Command::none()
+            },
+            Message::DirectoryRead(path, Ok(children)) => {
+                // the folder may have been swapped for another one meanwhile
+                if let Some(node) = self.tree.as_mut().and_then(|tree| tree.find_mut(&path)) {
+                    node.children = Some(children);
+                }
+
+                Command::none()
+            },
+            Message::DirectoryRead(_, Err(error)) => {
+                self.buffer_mut().error = Some(error);
+
+                Command::none()
+            },
+            Message::ToggleDirectory(path) => {
+                let Some(node) = self.tree.as_mut().and_then(|tree| tree.find_mut(&path)) else {
+                    return Command::none();
+                };
+
+                node.expanded = !node.expanded;
+
+                // subdirectories are read on first expansion, so big trees don't block
+                match (node.expanded, &node.children) {
+                    (true, None) => Command::perform(tree::read_dir(path.clone()), move |result| Message::DirectoryRead(path, result)),
+                    _ => Command::none(),
+                }
+            },
+            Message::OpenTreeFile(path) => {
+                let generation = self.start_opening();
+                Command::perform(load_file(path, self.settings.max_file_size()), move |result| Message::FileOpened(generation, result))
+            },
+            Message::FindInFilesChanged(query) => {
+                self.find_in_files.query = query;
+
+                Command::none()
+            },
+            Message::FindInFiles(query) => {
+                let Some(root) = self.search_root().map(Path::to_owned).filter(|_| !query.is_empty()) else {
+                    return Command::none();
+                };
+
+                // same options as the search bar
+                let Ok(pattern) = search::Pattern::new(&query, self.search.case_sensitive, self.search.regex) else {
+                    self.notify(String::from("Find in files: the query is not a valid regex"));
+                    return Command::none();
+                };
+
+                self.find_in_files.searching = true;
+                Command::perform(
+                    find_in_files::search(root, pattern, self.settings.max_file_size()),
+                    move |found| Message::FoundInFiles(query, found),
+                )
+            },
+            Message::FoundInFiles(query, found) => {
+                // a search for an older query finishing late
+                if query == self.find_in_files.query {
+                    self.find_in_files.found = Some(found);
+                    self.find_in_files.searching = false;
+                }
+
+                Command::none()
+            },
+            Message::OpenHit(path, position) => Command::perform(
+                load_file(path, self.settings.max_file_size()),
+                move |result| Message::FileOpenedAt(result, position),
+            ),
+            Message::RunExternal => {
+                let buffer = self.buffer();
+                let id = buffer.id;
+
+                match (buffer.path.is_some(), buffer.is_dirty) {
+                    // the tool should see what's in the editor
+                    (true, true) => {
+                        let backup = self.settings.backup_suffix.clone();
+
+                        match self.prepare_save(id) {
+                            Some(buffer) => Command::perform(buffer.save(backup), move |result| {
+                                Message::SavedBefore(Pending::Run(id), result)
+                            }),
+                            None => Command::none(),
+                        }
+                    },
+                    (true, false) => self.run_external(id),
+                    (false, _) => Command::none(),
+                }
+            },
+            Message::ExternalFinished(id, result) => {
+                let output = match result {
+                    Ok(output) => output,
+                    Err(error) => {
+                        if let Some(buffer) = self.find_mut(id) {
+                            buffer.error = Some(error);
+                        }
+
+                        return Command::none();
+                    },
+                };
+                let success = output.success;
+                self.external_output = Some(output);
+
+                // a formatter rewrites the file, so pick up what it did
+                match self.find(id).and_then(|buffer| Some((buffer.path.clone()?, buffer.modified))) {
+                    Some((path, modified)) if success => Command::perform(
+                        load_if_modified(path, modified, self.settings.max_file_size()),
+                        move |file| Message::CheckedOnDisk(id, file),
+                    ),
+                    _ => Command::none(),
+                }
+            },
+            Message::RunScript(name) => {
+                let buffer = self.buffer();
+                let (id, revision) = (buffer.id, buffer.revision());
+                let selection = buffer.content.selection();
+                let selected = selection.is_some();
+                let run = scripting::run(name.clone(), buffer.content.text(), selection);
+
+                self.notify(format!("Running {name}"));
+                Command::perform(run, move |result| Message::ScriptFinished(id, revision, selected, result))
+            },
+            Message::ScriptFinished(id, revision, selected, result) => {
+                match (result, self.find_mut(id)) {
+                    // typing went on while it ran, the edits win over the script
+                    (Ok(_), Some(buffer)) if buffer.revision() != revision => {
+                        self.notice = Some((String::from("The text changed while the script ran, so it was left alone"), Instant::now() + WARNING_DURATION));
+                    },
+                    (Ok(text), Some(buffer)) if selected && buffer.content.selection().is_some() => {
+                        buffer.perform(text_editor::Action::Edit(text_editor::Edit::Paste(Arc::new(text))));
+                    },
+                    (Ok(text), Some(buffer)) => buffer.apply(editor_core::Document { text, ..buffer.document() }),
+                    (Err(reason), Some(_)) => {
+                        self.notice = Some((format!("Script failed: {reason}"), Instant::now() + WARNING_DURATION));
+                    },
+                    (_, None) => {},
+                }
+
+                Command::none()
+            },
+            Message::ShowDiff => match self.buffer().path.clone() {
+                Some(path) => {
+                    let id = self.buffer().id;
+                    Command::perform(
+                        load_file(path, self.settings.max_file_size()),
+                        move |result| Message::DiskVersionRead(id, result),
+                    )
+                },
+                None => Command::none(),
+            },
+            Message::DiskVersionRead(id, result) => {
+                let Some(buffer) = self.find_mut(id) else {
+                    return Command::none();
+                };
+
+                match result {
+                    Ok(file) => {
+                        let diff = diff::Diff::new(buffer.name().to_owned(), &file.content, &buffer.content.text());
+
+                        if diff.is_empty() {
+                            self.diff = None;
+                            self.notify(String::from("No changes since the last save"));
+                        } else {
+                            self.diff = Some(diff);
+                        }
+                    },
+                    Err(error) => buffer.error = Some(error),
+                }
+
+                Command::none()
+            },
+            Message::CloseDiff => {
+                self.diff = None;
+
+                Command::none()
+            },
+            Message::CloseExternalOutput => {
+                self.external_output = None;
+
+                Command::none()
+            },
+            Message::CloseFindInFiles => {
+                self.find_in_files.found = None;
+
+                Command::none()
+            },
+            Message::ModifiersChanged(modifiers) => {
+                self.modifiers = modifiers;
+
+                Command::none()
+            },
+            // the widgets write the clipboard themselves, read back what they put there
+            Message::Copied => iced::clipboard::read(Message::ClipboardRead),
+            Message::ClipboardRead(Some(copied)) if self.settings.clipboard_history > 0 && !copied.is_empty() => {
+                self.clipboard.retain(|entry| *entry != copied);
+                self.clipboard.insert(0, copied);
+                self.clipboard.truncate(self.settings.clipboard_history);
+
+                Command::none()
+            },
+            Message::ClipboardRead(_) => Command::none(),
+            Message::PasteFromHistory if self.clipboard.is_empty() => {
+                self.notify(String::from("Nothing copied yet"));
+
+                Command::none()
+            },
+            Message::PasteFromHistory => {
+                let count = self.clipboard.len();
+                let previous = self.ring_paste;
+                let buffer = &mut self.buffers[self.active];
+
+                if buffer.read_only {
+                    return Command::none();
+                }
+
+                // pressed again right after, the last paste makes way for an older entry
+                let index = match previous {
+                    Some(paste) if paste.id == buffer.id
+                        && paste.revision == buffer.revision()
+                        && buffer.content.cursor_position() == paste.end =>
+                    {
+                        buffer.select(paste.start, paste.end);
+                        (paste.index + 1) % count
+                    },
+                    _ => 0,
+                };
+
+                let (start, end) = buffer.paste(self.clipboard[index].clone());
+                buffer.error = None;
+
+                self.ring_paste = Some(RingPaste { id: buffer.id, revision: buffer.revision(), index, start, end });
+                self.notify(format!("Pasted {} of {count} from the clipboard history", index + 1));
+
+                Command::none()
+            },
+            Message::ClearClipboardHistory => {
+                self.clipboard.clear();
+                self.ring_paste = None;
+                self.notify(String::from("Clipboard history cleared"));
+
+                Command::none()
+            },
+            Message::DeleteLine => {
+                self.buffer_mut().delete_line();
+
+                Command::none()
+            },
+            Message::ToggleSplit => {
+                self.split = !self.split;
+
+                Command::none()
+            },
+            // whichever decides the wrapping of this buffer, its profile or the global setting
+            Message::ToggleWrap => {
+                let path = self.buffer().path.clone();
+
+                match self.settings.profile_mut(path.as_deref()).and_then(|profile| profile.word_wrap.as_mut()) {
+                    Some(wrap) => *wrap = !*wrap,
+                    None => self.settings.word_wrap = !self.settings.word_wrap,
+                }
+
+                self.save_settings()
+            },
+            Message::ExtendBlock { down } => {
+                self.buffer_mut().extend_block(down);
+
+                Command::none()
+            },
+            Message::ShowEffectiveSettings => {
+                let source = match self.settings.profile(self.buffer().path.as_deref()) {
+                    Some((extension, _)) => format!("the {} profile", extension.trim_start_matches('.')),
+                    None => String::from("the global settings"),
+                };
+                let effective = self.effective();
+
+                self.notify(format!("From {source}: {effective}"));
+                Command::none()
+            },
+            // there's no history to edit on, read the file again with everything on
+            Message::ToggleReadOnly if self.buffer().light => {
+                let buffer = self.buffer_mut();
+                buffer.light = false;
+
+                let id = buffer.id;
+                self.reload(id)
+            },
+            Message::ToggleReadOnly => {
+                let buffer = self.buffer_mut();
+                buffer.read_only = !buffer.read_only;
+
+                Command::none()
+            },
+            Message::SetLanguage(language) => {
+                let buffer = self.buffer_mut();
+                buffer.language = Some(language);
+
+                // remembered per file, so the override sticks
+                match buffer.path.clone() {
+                    Some(path) => {
+                        self.settings.languages.insert(path, language);
+                        self.save_settings()
+                    },
+                    None => Command::none(),
+                }
+            },
+            Message::PinFile => match self.buffer().path.clone() {
+                Some(path) if !self.settings.pinned.contains(&path) => {
+                    self.settings.pinned.push(path);
+
+                    self.save_settings()
+                },
+                _ => Command::none(),
+            },
+            Message::UnpinFile(path) => {
+                self.settings.pinned.retain(|pinned| *pinned != path);
+
+                self.save_settings()
+            },
+            Message::MovePinLeft(path) => match self.settings.pinned.iter().position(|pinned| *pinned == path) {
+                Some(index) if index > 0 => {
+                    self.settings.pinned.swap(index - 1, index);
+
+                    self.save_settings()
+                },
+                _ => Command::none(),
+            },
+            // unlike a recent file, a pin stays when the file can't be read, it may be back later
+            Message::OpenPinned(path) => {
+                let generation = self.start_opening();
+                Command::perform(load_file(path, self.settings.max_file_size()), move |result| Message::FileOpened(generation, result))
+            },
+            Message::SetSyntaxTheme(theme) => {
+                // new highlighter settings, so the editor highlights again on the next draw
+                self.settings.syntax_theme = theme;
+
+                self.save_settings()
+            },
+            Message::LineEndingSelected(line_ending) => {
+                let buffer = self.buffer_mut();
+                buffer.is_dirty |= line_ending != buffer.line_ending;
+                buffer.line_ending = line_ending;
+
+                Command::none()
+            },
+            Message::SaveWithEncoding(encoding) => {
+                let buffer = self.buffer_mut();
+                buffer.is_dirty |= encoding != buffer.encoding;
+                buffer.encoding = encoding;
+
+                self.update(Message::Save)
+            },
+            Message::Replace { find, replace } => {
+                // an invalid regex is already pointed out in the search bar
+                let Ok(pattern) = search::Pattern::new(&find, self.search.case_sensitive, self.search.regex) else {
+                    return Command::none();
+                };
+                let buffer = self.buffer_mut();
+                let text = buffer.content.text();
+                let replaced = pattern.replace_all(&text, &replace);
+
+                if replaced != text {
+                    buffer.replace_text(replaced);
+                }
+
+                Command::none()
+            },
+            Message::Save => {
+                let buffer = self.buffer();
+                let (id, revision) = (buffer.id, buffer.revision());
+
+                match self.formatter(buffer) {
+                    Some(command) => Command::perform(
+                        external::format(command, buffer.content.text()),
+                        move |result| Message::Formatted(id, revision, result),
+                    ),
+                    None => self.save(id),
+                }
+            },
+            Message::Formatted(id, revision, result) => {
+                match (result, self.find_mut(id)) {
+                    // typing went on while it ran, the edits win over the formatting
+                    (Ok(_), Some(buffer)) if buffer.revision() != revision => {},
+                    (Ok(text), Some(buffer)) => buffer.apply(editor_core::Document { text, ..buffer.document() }),
+                    (Err(reason), Some(_)) => {
+                        self.notice = Some((format!("Saved without formatting: {reason}"), Instant::now() + WARNING_DURATION));
+                    },
+                    (_, None) => return Command::none(),
+                }
+
+                self.save(id)
+            },
+            Message::SaveAs => {
+                let id = self.buffer().id;
+                let generation = self.next_generation();
+                let backup = self.settings.backup_suffix.clone();
+                let Some(buffer) = self.prepare_save(id) else {
+                    return Command::none();
+                };
+                buffer.saving = Some(generation);
+                let save = buffer.save_as(backup);
+
+                self.dialog_open = true;
+                Command::perform(save, move |result| Message::FileSaved(id, generation, result))
+            },
+            // a newer save of the same buffer will report instead
+            Message::FileSaved(id, generation, _) if self.find(id).is_some_and(|buffer| buffer.saving != Some(generation)) => {
+                Command::none()
+            },
+            // a directory can't be saved over, so ask for a file name again
+            Message::FileSaved(id, _, Err(Error::IsDirectory(path))) => {
+                let generation = self.next_generation();
+                let backup = self.settings.backup_suffix.clone();
+                let Some(buffer) = self.find_mut(id) else {
+                    return Command::none();
+                };
+
+                buffer.error = Some(Error::IsDirectory(path));
+                buffer.saving = Some(generation);
+                let save = buffer.save_as(backup);
+
+                self.dialog_open = true;
+                Command::perform(save, move |result| Message::FileSaved(id, generation, result))
+            },
+            Message::FileSaved(id, _, result) => {
+                self.dialog_open = false;
+
+                let Some(buffer) = self.find_mut(id) else {
+                    return Command::none();
+                };
+                buffer.saving = None;
+
+                let saved = result.as_ref().ok().cloned();
+                buffer.apply(buffer.document().saved(result));
+
+                match saved {
+                    Some(path) => {
+                        let cursor = buffer.content.cursor_position();
+
+                        // a warning outlasts a notice, don't cover it up
+                        let warning = self.notice.as_ref().is_some_and(|(_, until)| *until > Instant::now() + NOTICE_DURATION);
+                        if !warning {
+                            self.notify(format!("Saved {}", path.display()));
+                        }
+                        self.settings.cursors.insert(path.clone(), cursor);
+                        self.mirrored.remove(&id);
+
+                        Command::batch([self.save_settings(), Command::perform(recovery::remove(path), Message::RecoveryUpdated)])
+                    },
+                    None => Command::none(),
+                }
+            },
+            Message::FileOpened(_, Err(error)) => {
+                self.opening = None;
+                self.dialog_open = false;
+
+                let buffer = self.buffer_mut();
+                buffer.apply(buffer.document().opened(Err(error)));
+        
+                Command::none()
+            }
+            Message::Undo => {
+                self.buffer_mut().undo();
+
+                Command::none()
+            },
+            Message::Redo => {
+                self.buffer_mut().redo();
+
+                Command::none()
+            },
+        };
+
+        self.refresh_search();
+        Command::batch([command, self.refresh_preview(), self.refresh_validation()])
+    }
+
+    fn view(&self) -> Element<'_, Message> {
+        let buffer = self.buffer();
+
+        let tabs = self.buffers.iter().enumerate().fold(row![].spacing(2), |tabs, (index, tab)| {
+            let dirty = if tab.is_dirty { "* " } else { "" };
+            let style = || if index == self.active {
+                theme::Button::Primary
+            } else {
+                theme::Button::Secondary
+            };
+
+            tabs.push(
+                row![
+                    button(text(format!("{dirty}{}", tab.name())).size(14))
+                        .style(style())
+                        .on_press(Message::SelectTab(index)),
+                    button(text("×").size(14))
+                        .style(style())
+                        .on_press(Message::CloseTab(index))]
+            )
+        });
+
+        // only Markdown has something to preview
+        let preview = button("Preview");
+        let preview = match buffer.language() {
+            Language::Markdown => preview.on_press(Message::TogglePreview),
+            _ => preview,
+        };
+
+        let controls = row![
+            button("New").on_press(Message::New),
+            button("Open").on_press(Message::Open),
+            button("Open Folder").on_press(Message::OpenFolder),
+            button("Save").on_press(Message::Save),
+            button("Save As").on_press(Message::SaveAs),
+            button("Undo").on_press(Message::Undo),
+            button("Redo").on_press(Message::Redo),
+            button(if buffer.read_only { "Editable" } else { "Read-only" }).on_press(Message::ToggleReadOnly),
+            horizontal_space(Length::Fill),
+            button(if self.split { "Unsplit" } else { "Split" }).on_press(Message::ToggleSplit),
+            button("Minimap").on_press(Message::ToggleMinimap),
+            preview,
+            button("Wrap").on_press(Message::ToggleWrap),
+            button("Line numbers").on_press(Message::ToggleLineNumbers),
+            button("Theme").on_press(Message::ToggleTheme)]
+            .spacing(5);
+        
+        // split panes share the content but each gets its own scrollable
+        let input: Element<'_, Message> = if self.split {
+            column![self.pane(), self.pane()].spacing(5).into()
+        } else {
+            self.pane()
+        };
+
+        // drawing it costs a pass over every line, so it's opt-in
+        let input: Element<'_, Message> = match self.settings.minimap {
+            true => {
+                let minimap = minimap::Minimap {
+                    lines: buffer.stats.lines.clone(),
+                    cursor_line: buffer.content.cursor_position().0,
+                    // the bars around the editor take roughly this much of the window
+                    visible_lines: ((self.window_height - 150.0) / (self.settings.font_size * LINE_HEIGHT)).max(1.0) as usize,
+                };
+
+                row![input, canvas(minimap).width(80).height(Length::Fill)].spacing(5).into()
+            },
+            false => input,
+        };
+
+        let input: Element<'_, Message> = match &self.preview {
+            Some(preview) if buffer.language() == Language::Markdown => {
+                let preview = container(preview.view(self.settings.font_size)).width(Length::FillPortion(1));
+                row![container(input).width(Length::FillPortion(1)), preview].spacing(5).into()
+            },
+            _ => input,
+        };
+
+        let input: Element<'_, Message> = match &self.tree {
+            Some(tree) if !self.zen => row![sidebar(tree, &self.find_in_files), input].spacing(5).into(),
+            _ => input,
+        };
+
+        let recent_bar = (!self.settings.recent.is_empty()).then(|| {
+            self.settings.recent.iter().fold(row![text("Recent:").size(14)].spacing(5), |bar, path| {
+                let name = path
+                    .file_name()
+                    .and_then(|name| name.to_str())
+                    .unwrap_or_default();
+
+                bar.push(button(text(name).size(12)).on_press(Message::OpenRecent(path.clone())))
+            })
+        });
+
+        let pinned_bar = (!self.settings.pinned.is_empty()).then(|| {
+            self.settings.pinned.iter().enumerate().fold(row![text("Pinned:").size(14)].spacing(5), |bar, (index, path)| {
+                let name = path
+                    .file_name()
+                    .and_then(|name| name.to_str())
+                    .unwrap_or_default();
+                let small = |label| button(text(label).size(12)).style(theme::Button::Text);
+
+                let mut pin = row![button(text(name).size(12)).on_press(Message::OpenPinned(path.clone()))];
+                if index > 0 {
+                    pin = pin.push(small("‹").on_press(Message::MovePinLeft(path.clone())));
+                }
+
+                bar.push(pin.push(small("×").on_press(Message::UnpinFile(path.clone()))))
+            })
+        });
+
+        let go_to_line_bar = self.go_to_line.as_ref().map(|go_to_line| {
+            let mut bar = row![
+                text("Go to line:").size(14),
+                text_input("Line number", &go_to_line.input)
+                    .id(go_to_line_id())
+                    .on_input(Message::GoToLineChanged)
+                    .on_submit(Message::GoToLineSubmitted)
+                    .width(120),
+                button("Go").on_press(Message::GoToLineSubmitted),
+                button("Cancel").on_press(Message::ToggleGoToLine)]
+                .spacing(5)
+                .align_items(Alignment::Center);
+
+            if let Some(error) = &go_to_line.error {
+                bar = bar.push(text(error).size(14).style(color!(0xff5555)));
+            }
+
+            bar
+        });
+
+        let palette = self.palette.as_ref().map(|palette| {
+            let input = text_input("Type a command", &palette.query)
+                .id(palette_id())
+                .on_input(Message::PaletteChanged)
+                .on_submit(Message::PaletteSubmitted);
+
+            let entries = palette::filter(&palette.query, self.buffer()).into_iter().fold(column![].spacing(2), |entries, entry| {
+                entries.push(
+                    button(text(entry.name).size(14))
+                        .style(theme::Button::Secondary)
+                        .width(Length::Fill)
+                        .on_press(Message::RunCommand(Box::new(entry.message))))
+            });
+            let entries = palette::filter_scripts(&palette.query, &palette.scripts).into_iter().fold(entries, |entries, (name, message)| {
+                entries.push(
+                    button(text(name).size(14))
+                        .style(theme::Button::Secondary)
+                        .width(Length::Fill)
+                        .on_press(Message::RunCommand(Box::new(message))))
+            });
+
+            container(column![input, scrollable(entries).height(Length::Fixed(240.0))].spacing(5))
+                .padding(5)
+                .style(theme::Container::Box)
+        });
+
+        let search_bar = self.search.visible.then(|| {
+            let query = self.search.query.clone();
+            let replace = Message::Replace {
+                find: self.search.query.clone(),
+                replace: self.search.replacement.clone(),
+            };
+
+            // "3 of 17" once stepping, before that just how many there are
+            let count = match (self.search.query.is_empty(), self.search.error(), self.search.count(buffer.id)) {
+                (true, _, _) | (false, None, None) => text(""),
+                (false, Some(error), _) => text(error).style(color!(0xff5555)),
+                (false, None, Some((Some(current), total))) => text(format!("{} of {total}", current + 1)),
+                (false, None, Some((None, 1))) => text("1 match"),
+                (false, None, Some((None, total))) => text(format!("{total} matches")),
+            };
+
+            row![
+                text_input("Find", &self.search.query)
+                    .on_input(Message::SearchChanged)
+                    .on_submit(Message::Find(query.clone())),
+                count.size(14),
+                text_input("Replace", &self.search.replacement)
+                    .on_input(Message::ReplacementChanged)
+                    .on_submit(replace.clone()),
+                checkbox("Case sensitive", self.search.case_sensitive, Message::CaseSensitiveToggled),
+                checkbox("Regex", self.search.regex, Message::RegexToggled),
+                button("Next").on_press(Message::Find(query)),
+                button("Replace all").on_press(replace)]
+                .spacing(5)
+        });
+
+        
+        let status_bar = {
+            let status = match buffer.error.as_ref() {
+                Some(error) => text(error.to_string()),
+                None => match (&self.notice, buffer.display_path()) {
+                (Some((notice, _)), _) => text(notice).size(14),
+                (None, Some(path)) => text(path).size(14),
+                (None, None) if buffer.scratch => text("Scratch"),
+                (None, None) => text("New File"),
+                }
+            };
+            
+            let position = {
+                let (line, column) = buffer.content.cursor_position();
+                let visual = buffer.visual_column(self.effective().tab_width);
+
+                // compilers count chars, the visual column is where it shows with tabs expanded
+                let column = match visual == column {
+                    true => format!("Col {}", column + 1),
+                    false => format!("Col {} (visual {})", column + 1, visual + 1),
+                };
+
+                let selected = match buffer.content.selection() {
+                    Some(selection) if selection.contains('\n') => format!(
+                        " ({} selected, {} lines)",
+                        selection.chars().count(),
+                        selection.lines().count(),
+                    ),
+                    Some(selection) => format!(" ({} selected)", selection.chars().count()),
+                    None => String::new(),
+                };
+                
+                let cursors = match buffer.cursor_count() {
+                    1 => String::new(),
+                    count => format!(" · {count} cursors"),
+                };
+
+                text(format!("Ln {}, {column}{selected}{cursors}", line + 1))
+            };
+
+            let mode = text(match (buffer.light, buffer.read_only) {
+                (true, _) => "READ-ONLY · LARGE FILE",
+                (false, true) => "READ-ONLY",
+                (false, false) => "",
+            });
+
+            let stats = text(format!("{} words · {} chars", buffer.stats.words, buffer.stats.chars));
+
+            let line_ending = pick_list(
+                &LineEnding::ALL[..],
+                Some(buffer.line_ending),
+                Message::LineEndingSelected,
+            )
+            .text_size(14);
+
+            let encoding = pick_list(
+                &Encoding::SAVABLE[..],
+                Some(buffer.encoding),
+                Message::SaveWithEncoding,
+            )
+            .text_size(14);
+
+            let language = pick_list(
+                &Language::ALL[..],
+                Some(buffer.language()),
+                Message::SetLanguage,
+            )
+            .text_size(14);
+
+            let syntax_theme = pick_list(
+                &highlight::SyntaxTheme::ALL[..],
+                Some(self.settings.syntax_theme),
+                Message::SetSyntaxTheme,
+            )
+            .text_size(14);
+
+            let zoom = text(format!("{:.0}%", self.settings.font_size / DEFAULT_FONT_SIZE * 100.0));
+
+            // the editor can't draw whitespace marks, so the current line's is spelled out here
+            let whitespace = match self.settings.show_whitespace {
+                true => {
+                    let (spaces, tabs, trailing) = buffer.line_whitespace();
+                    text(format!("·{spaces} →{tabs} trailing {trailing} ↵{}", buffer.line_ending))
+                },
+                false => text(""),
+            };
+
+            let reveal: Element<'_, Message> = match &buffer.path {
+                Some(_) => button(text("Reveal").size(14))
+                    .style(theme::Button::Text)
+                    .on_press(Message::RevealInFileManager)
+                    .into(),
+                None => horizontal_space(Length::Fixed(0.0)).into(),
+            };
+
+            // clicking goes to where the parser gave up
+            let problem: Element<'_, Message> = match &self.validation {
+                validate::Validation { problem: Some(problem), checked: Some((id, _)), .. } if *id == buffer.id => {
+                    button(text(format!("Ln {}, Col {}: {}", problem.line, problem.column, problem.message)).size(14).style(color!(0xff5555)))
+                        .style(theme::Button::Text)
+                        .on_press(Message::MoveCursor((problem.line, problem.column)))
+                        .into()
+                },
+                _ => horizontal_space(Length::Fixed(0.0)).into(),
+            };
+
+            row![status, reveal, horizontal_space(Length::Fill), problem, whitespace, mode, zoom, stats, syntax_theme, language, encoding, line_ending, position].spacing(10)
+        };
+        // the keymap doesn't live in the chrome, so zen mode keeps every shortcut
+        let mut layout = match self.zen {
+            true => column![].spacing(5),
+            false => column![tabs, controls].spacing(5),
+        };
+        if let Some(pinned_bar) = pinned_bar.filter(|_| !self.zen) {
+            layout = layout.push(pinned_bar);
+        }
+        if let Some(recent_bar) = recent_bar.filter(|_| !self.zen) {
+            layout = layout.push(recent_bar);
+        }
+        if let Some(search_bar) = search_bar {
+            layout = layout.push(search_bar);
+        }
+        if let Some(go_to_line_bar) = go_to_line_bar {
+            layout = layout.push(go_to_line_bar);
+        }
+        if let Some(palette) = palette {
+            layout = layout.push(palette);
+        }
+
+        layout = layout.push(input);
+        if let Some(results) = self.search_root().and_then(|root| self.find_in_files.view(root)) {
+            layout = layout.push(results);
+        }
+        if let Some(output) = &self.external_output {
+            layout = layout.push(output.view());
+        }
+        if let Some(diff) = &self.diff {
+            layout = layout.push(diff.view(self.settings.font_size));
+        }
+
+        if !self.zen {
+            layout = layout.push(status_bar);
+        }
+
+        container(layout).padding(5).into()
+    }
+
+    fn theme(&self) -> Theme {
+        self.settings.theme.into()
+    }
+
+    fn subscription(&self) -> Subscription<Message> {
+        // no shortcuts while a dialog is open, so we don't stack pick_files futures
+        let shortcuts = if self.dialog_open {
+            Subscription::none()
+        } else {
+            // the keymap lives in our state, so the lookup happens in update
+            subscription::events_with(key_pressed)
+        };
+
+        let autosave = match self.settings.autosave_secs {
+            Some(secs) if secs > 0 => time::every(Duration::from_secs(secs)).map(|_| Message::AutoSave),
+            _ => Subscription::none(),
+        };
+
+        let recovery = match self.settings.recovery_secs {
+            Some(secs) if secs > 0 && self.buffers.iter().any(|buffer| buffer.is_dirty && buffer.path.is_some()) => {
+                time::every(Duration::from_secs(secs)).map(|_| Message::MirrorRecovery)
+            },
+            _ => Subscription::none(),
+        };
+
+        let dropped = subscription::events_with(file_dropped);
+        let modifiers = subscription::events_with(modifiers_changed);
+        let copied = subscription::events_with(copied);
+        let window = subscription::events_with(window_event);
+
+        let watched = if self.settings.watch_files {
+            watch::files(self.buffers.iter().filter_map(|buffer| buffer.path.clone()).collect())
+        } else {
+            Subscription::none()
+        };
+
+        // only tick while there's a notice to expire
+        let notice = match self.notice {
+            Some(_) => time::every(Duration::from_millis(250)).map(Message::Tick),
+            None => Subscription::none(),
+        };
+
+        // stats are recounted in the background once typing settles
+        let stats = if self.buffers.iter().any(Buffer::is_stale) {
+            time::every(Duration::from_millis(50)).map(Message::RefreshStats)
+        } else {
+            Subscription::none()
+        };
+
+        Subscription::batch([shortcuts, autosave, recovery, dropped, modifiers, copied, window, watched, notice, stats])
+    }
+    
+}
+
+impl Editor {
+    /// the buffer shown in the editor
+    fn buffer(&self) -> &Buffer {
+        &self.buffers[self.active]
+    }
+
+    fn buffer_mut(&mut self) -> &mut Buffer {
+        &mut self.buffers[self.active]
+    }
+
+    fn find(&self, id: usize) -> Option<&Buffer> {
+        self.buffers.iter().find(|buffer| buffer.id == id)
+    }
+
+    fn find_mut(&mut self, id: usize) -> Option<&mut Buffer> {
+        self.buffers.iter_mut().find(|buffer| buffer.id == id)
+    }
+
+    fn next_id(&mut self) -> usize {
+        self.next_id += 1;
+        self.next_id - 1
+    }
+
+    /// show a loaded file, reusing its tab if it's already open
+    fn open_buffer(&mut self, file: LoadedFile, light: bool) {
+        if let Some(index) = self.buffers.iter().position(|buffer| buffer.path.as_ref() == Some(&file.path)) {
+            self.active = index;
+            return;
+        }
+
+        let language = self.settings.languages.get(&file.path).copied();
+        let mut buffer = match light {
+            true => Buffer::light(self.next_id(), file),
+            false => Buffer::from_file(self.next_id(), file),
+        };
+        buffer.language = language;
+
+        if self.buffer().is_pristine() {
+            self.buffers[self.active] = buffer;
+        } else {
+            self.buffers.push(buffer);
+            self.active = self.buffers.len() - 1;
+        }
+    }
+
+    /// drop a buffer, keeping at least one tab around
+    fn close(&mut self, id: usize) -> Command<Message> {
+        let Some(index) = self.buffers.iter().position(|buffer| buffer.id == id) else {
+            return Command::none();
+        };
+
+        let buffer = self.buffers.remove(index);
+        let command = self.remember_cursor(&buffer);
+        self.mirrored.remove(&id);
+
+        if let Some(path) = buffer.path.clone() {
+            self.closed.retain(|(closed, _)| *closed != path);
+            self.closed.push((path, buffer.content.cursor_position()));
+
+            if self.closed.len() > MAX_CLOSED {
+                self.closed.remove(0);
+            }
+        }
+
+        // closing saved it or threw the changes away, either way the copy is done with
+        let command = match buffer.path.clone() {
+            Some(path) => Command::batch([command, Command::perform(recovery::remove(path), Message::RecoveryUpdated)]),
+            None => command,
+        };
+
+        if self.buffers.is_empty() {
+            let buffer = Buffer::new(self.next_id());
+            self.buffers.push(buffer);
+        }
+
+        if self.active > index || self.active >= self.buffers.len() {
+            self.active = self.active.saturating_sub(1);
+        }
+
+        command
+    }
+
+    /// show a freshly loaded file, then move its cursor to the 1-based `position`
+    fn opened(&mut self, file: LoadedFile, position: Option<(usize, usize)>) -> Command<Message> {
+        let is_open = self.buffers.iter().any(|buffer| buffer.path.as_ref() == Some(&file.path));
+        let is_big = self.settings.light_file_size().is_some_and(|size| file.content.len() as u64 > size);
+
+        if is_big && !is_open {
+            self.dialog_open = true;
+            let name = file.path.file_name().unwrap_or_default().to_string_lossy().into_owned();
+            let ask = ask_light(name, file.content.len() as u64);
+
+            return Command::perform(ask, move |light| Message::LightAnswered(file, position, light));
+        }
+
+        self.show(file, position, false)
+    }
+
+    /// put a loaded file in a buffer, read-only and without a history if `light`
+    fn show(&mut self, file: LoadedFile, position: Option<(usize, usize)>, light: bool) -> Command<Message> {
+        self.dialog_open = false;
+        self.open_buffer(file, light);
+        self.settings.last_open_path = self.buffer().path.clone();
+        self.remember_recent();
+
+        // the cursor can only move once the buffer is there
+        match position {
+            Some(position) => Command::batch([
+                self.save_settings(),
+                Command::perform(async move { position }, Message::MoveCursor),
+            ]),
+            None => self.save_settings(),
+        }
+    }
+
+    /// store where the cursor is in a buffer's file, for the next time it's opened
+    fn remember_cursor(&mut self, buffer: &Buffer) -> Command<Message> {
+        let Some(path) = buffer.path.clone() else {
+            return Command::none();
+        };
+
+        self.settings.cursors.insert(path, buffer.content.cursor_position());
+        self.save_settings()
+    }
+
+    /// read a buffer's file from disk again
+    fn reload(&self, id: usize) -> Command<Message> {
+        match self.find(id).and_then(|buffer| buffer.path.clone()) {
+            Some(path) => Command::perform(
+                load_file(path, self.settings.max_file_size()),
+                move |result| Message::Reloaded(id, result),
+            ),
+            None => Command::none(),
+        }
+    }
+
+    /// ask for a file and load it
+    fn open(&mut self) -> Command<Message> {
+        self.dialog_open = true;
+        let generation = self.start_opening();
+        Command::perform(pick_files(self.settings.max_file_size()), move |files| Message::FilesOpened(generation, files))
+    }
+
+    /// a fresh generation, for a load or save about to start
+    fn next_generation(&mut self) -> usize {
+        self.generation += 1;
+        self.generation
+    }
+
+    /// a generation for a file about to be opened, overtaking any still loading
+    fn start_opening(&mut self) -> usize {
+        let generation = self.next_generation();
+        self.opening = Some(generation);
+        generation
+    }
+
+    /// forget a file still loading, its result no longer has a place to go
+    fn cancel_opening(&mut self) {
+        if self.opening.take().is_some() {
+            self.dialog_open = false;
+        }
+    }
+
+    /// ask what to do with unsaved changes before running `pending`
+    fn confirm_discard(&mut self, pending: Pending) -> Command<Message> {
+        self.dialog_open = true;
+        Command::perform(ask_unsaved(), move |choice| Message::Unsaved(pending, choice))
+    }
+
+    /// run an operation once the unsaved changes are dealt with
+    fn proceed(&mut self, pending: Pending) -> Command<Message> {
+        self.dialog_open = false;
+
+        match pending {
+            Pending::Close(id) => self.close(id),
+            Pending::Quit(id) => {
+                // saved or discarded, either way it no longer holds up quitting
+                if let Some(buffer) = self.find_mut(id) {
+                    buffer.is_dirty = false;
+                }
+
+                self.quit()
+            },
+            Pending::Run(id) => self.run_external(id),
+        }
+    }
+
+    /// write a buffer to its file, asking for one if it has none
+    fn save(&mut self, id: usize) -> Command<Message> {
+        let backup = self.settings.backup_suffix.clone();
+        let generation = self.next_generation();
+        let Some(buffer) = self.prepare_save(id) else {
+            return Command::none();
+        };
+        buffer.saving = Some(generation);
+        let save = buffer.save(backup);
+        let asks_path = buffer.path.is_none(); // save_file will ask for a path
+
+        self.dialog_open = asks_path;
+        Command::perform(save, move |result| Message::FileSaved(id, generation, result))
+    }
+
+    /// the formatter to run before saving `buffer`, if formatting on save applies
+    fn formatter(&self, buffer: &Buffer) -> Option<String> {
+        let extension = buffer.path.as_deref()?.extension()?.to_str()?;
+
+        self.settings.formatters.get(extension).filter(|_| self.settings.format_on_save).cloned()
+    }
+
+    /// run the external command from the settings on a buffer's file
+    fn run_external(&mut self, id: usize) -> Command<Message> {
+        let Some(template) = self.settings.external_command.clone().filter(|template| !template.trim().is_empty()) else {
+            self.notify(String::from("No external command, set `external_command` in the settings"));
+            return Command::none();
+        };
+        let Some(path) = self.find(id).and_then(|buffer| buffer.path.clone()) else {
+            return Command::none();
+        };
+
+        self.notify(format!("Running {}", template.replace("{path}", &path.display().to_string())));
+        Command::perform(external::run(template, path), move |result| Message::ExternalFinished(id, result))
+    }
+
+    /// close the window, asking about each dirty buffer in turn first
+    fn quit(&mut self) -> Command<Message> {
+        if let Some(index) = self.buffers.iter().position(|buffer| buffer.is_dirty) {
+            self.active = index;
+            return self.confirm_discard(Pending::Quit(self.buffers[index].id));
+        }
+
+        for buffer in &self.buffers {
+            if let Some(path) = &buffer.path {
+                self.settings.cursors.insert(path.clone(), buffer.content.cursor_position());
+            }
+        }
+
+        let settings = self.settings.clone();
+        let mirrored: Vec<_> = self.buffers.iter().filter_map(|buffer| buffer.path.clone()).collect();
+        let session = self.settings.restore_session.then(|| session::Session {
+            paths: self.buffers.iter().filter_map(|buffer| buffer.path.clone()).collect(),
+            active: self.buffers[..self.active].iter().filter(|buffer| buffer.path.is_some()).count(),
+        });
+
+        // wait for everything to be written, closing would drop the futures
+        Command::perform(
+            async move {
+                let _ = settings.save().await;
+
+                if let Some(session) = session {
+                    let _ = session.save().await;
+                }
+
+                // a clean exit, nothing left to recover
+                for path in mirrored {
+                    let _ = recovery::remove(path).await;
+                }
+            },
+            |_| Message::Exit,
+        )
+    }
+
+    /// where find in files looks: the open folder, else the active file's project
+    fn search_root(&self) -> Option<&Path> {
+        self.tree.as_ref().map(|tree| tree.path.as_path()).or(self.buffer().project.as_deref())
+    }
+
+    /// load a file from the recent list
+    fn open_recent(&mut self, path: PathBuf) -> Command<Message> {
+        Command::perform(load_file(path.clone(), self.settings.max_file_size()), move |result| Message::RecentOpened(path, result))
+    }
+
+    /// move the current file to the front of the recent list
+    fn remember_recent(&mut self) {
+        if let Some(path) = &self.buffers[self.active].path {
+            self.settings.recent.retain(|recent| recent != path);
+            self.settings.recent.push_front(path.clone());
+            self.settings.recent.truncate(MAX_RECENT);
+        }
+    }
+
+    /// recount the search matches in the active buffer, once its stats are
+    /// up to date too, so typing in a big file doesn't rescan on every key
+    fn refresh_search(&mut self) {
+        let buffer = &self.buffers[self.active];
+
+        if self.search.visible && !buffer.is_stale() && !self.search.is_fresh(buffer.id, buffer.revision()) {
+            self.search.refresh(buffer.id, buffer.revision(), &buffer.content.text());
+        }
+    }
+
+    /// render the preview again once typing pauses, one render at a time
+    fn refresh_preview(&mut self) -> Command<Message> {
+        let buffer = &self.buffers[self.active];
+        let key = (buffer.id, buffer.revision());
+
+        let Some(preview) = &mut self.preview else {
+            return Command::none();
+        };
+
+        if buffer.language() != Language::Markdown
+            || buffer.is_stale()
+            || preview.rendered == Some(key)
+            || preview.pending.is_some()
+        {
+            return Command::none();
+        }
+
+        preview.pending = Some(key);
+        Command::perform(preview::render(buffer.content.text()), move |blocks| Message::PreviewRendered(key, blocks))
+    }
+
+    /// check a JSON or TOML buffer again once typing settles
+    fn refresh_validation(&mut self) -> Command<Message> {
+        let buffer = &self.buffers[self.active];
+        let key = (buffer.id, buffer.revision());
+
+        let Some(format) = validate::Format::of(buffer.path.as_deref(), buffer.language()) else {
+            return Command::none();
+        };
+
+        if buffer.is_stale() || self.validation.checked == Some(key) || self.validation.pending.is_some() {
+            return Command::none();
+        }
+
+        self.validation.pending = Some(key);
+        Command::perform(validate::check(format, buffer.content.text()), move |problem| Message::Validated(key, problem))
+    }
+
+    /// tidy up a buffer the user is about to save, as the settings ask
+    fn prepare_save(&mut self, id: usize) -> Option<&Buffer> {
+        let effective = self.settings.effective(self.find(id)?.path.as_deref());
+        let options = editor_core::SaveOptions {
+            trim_whitespace: effective.trim_whitespace,
+            final_newline: effective.final_newline,
+        };
+        let buffer = self.find_mut(id)?;
+
+        buffer.apply(buffer.document().prepared(options));
+        Some(buffer)
+    }
+
+    /// the editor for the active buffer, with its gutter and scrollable
+    fn pane(&self) -> Element<'_, Message> {
+        let buffer = self.buffer();
+        let effective = self.effective();
+        let dark = self.settings.theme == settings::ThemeName::Dark;
+
+        let input = text_editor(&buffer.content)
+            .on_edit(Message::Edit)
+            .font(self.font)
+            .size(self.settings.font_size)
+            .highlight::<highlight::Highlighter>(
+                highlight::Settings {
+                    syntax: highlight::settings(buffer.language(), self.settings.syntax_theme, dark),
+                    query: if self.search.visible { self.search.query.clone() } else { String::new() },
+                    case_sensitive: self.search.case_sensitive,
+                    regex: self.search.regex,
+                    match_color: self.theme().palette().primary,
+                    cursors: buffer.extra_highlights(),
+                },
+                |format, _theme| *format,
+            );
+
+        let code = !matches!(buffer.language(), Language::PlainText | Language::Markdown);
+        let rulers = match self.settings.show_rulers {
+            true => self.settings.rulers.clone(),
+            false => Vec::new(),
+        };
+        // guides go by line number, which only holds while lines don't wrap and
+        // the editor is scrolled from outside, not by itself
+        let scrolled_outside = self.settings.line_numbers || self.split || self.settings.scroll_past_end;
+        let guides = self.settings.indent_guides && code && !effective.word_wrap && scrolled_outside;
+
+        let input: Element<'_, Message> = match guides || !rulers.is_empty() {
+            true => {
+                let overlay = overlay::Overlay::new(input, self.settings.font_size).rulers(rulers.clone());
+
+                match guides {
+                    true => overlay
+                        .guides(buffer.stats.lines.clone(), buffer.stats.tabs.clone(), effective.tab_width)
+                        .into(),
+                    false => overlay.into(),
+                }
+            },
+            false => input.into(),
+        };
+
+        // without wrapping the editor is made wide enough for its longest line,
+        // and a scrollable lets us reach the end of it
+        let (input, direction): (Element<'_, Message>, _) = if effective.word_wrap {
+            (input, scrollable::Direction::Vertical(scrollable::Properties::default()))
+        } else {
+            // and for the rulers, so they show next to short lines too
+            let columns = rulers.iter().copied().max().unwrap_or(0).max(buffer.stats.longest_line);
+            let width = columns as f32 * self.settings.font_size * CHAR_WIDTH + 20.0;
+
+            (container(input).width(width).into(), scrollable::Direction::Both {
+                vertical: scrollable::Properties::default(),
+                horizontal: scrollable::Properties::default(),
+            })
+        };
+
+        // the window is close enough to the viewport for this
+        let past_end = match self.settings.scroll_past_end {
+            true => self.window_height * self.settings.scroll_past_end_screens.max(0.0),
+            false => 0.0,
+        };
+
+        if self.settings.line_numbers {
+            let numbers = (1..=buffer.content.line_count())
+                .map(|line| line.to_string())
+                .collect::<Vec<_>>()
+                .join("\n");
+
+            let gutter = container(
+                text(numbers)
+                    .font(self.font) // must match the editor's for the lines to line up
+                    .size(self.settings.font_size)
+                    .horizontal_alignment(alignment::Horizontal::Right),
+            )
+            .padding([5, 5]); // same as the editor, so the lines stay aligned
+
+            // sharing one scrollable is what keeps the gutter in sync with the editor
+            scrollable(column![row![gutter, input], vertical_space(past_end)])
+                .direction(direction)
+                .height(Length::Fill)
+                .into()
+        } else if self.split || self.settings.scroll_past_end {
+            // the editor's own scroll lives in the shared content, so a split pane scrolls outside it;
+            // and it stops at the last line, so room past it needs the outer scroll too
+            scrollable(column![input, vertical_space(past_end)]).direction(direction).height(Length::Fill).into()
+        } else if effective.word_wrap {
+            input
+        } else {
+            scrollable(input)
+                .direction(scrollable::Direction::Horizontal(scrollable::Properties::default()))
+                .height(Length::Fill)
+                .into()
+        }
+    }
+
+    /// whether incrementing by `delta` would change the selection
+    fn changes_numbers(&self, delta: i64) -> bool {
+        self.buffer()
+            .content
+            .selection()
+            .is_some_and(|selection| increment_numbers(&selection, delta) != selection)
+    }
+
+    /// the settings the active buffer is edited with
+    fn effective(&self) -> settings::Effective {
+        self.settings.effective(self.buffer().path.as_deref())
+    }
+
+    /// show a message in the status bar for a little while
+    fn notify(&mut self, notice: String) {
+        self.notice = Some((notice, Instant::now() + NOTICE_DURATION));
+    }
+
+    /// persist the current settings in the background
+    fn save_settings(&self) -> Command<Message> {
+        Command::perform(self.settings.clone().save(), Message::SettingsSaved)
+    }
+}
+
+/// the open folder as an indented list under a find-in-files box,
+/// directories toggle and files open
+fn sidebar<'a>(tree: &'a tree::Node, find_in_files: &'a find_in_files::FindInFiles) -> Element<'a, Message> {
+    let rows = tree.rows().into_iter().fold(column![].spacing(2), |rows, (depth, node)| {
+        let indent = "  ".repeat(depth);
+        let (label, message) = match node.is_dir {
+            true => (
+                format!("{indent}{} {}", if node.expanded { "▾" } else { "▸" }, node.name),
+                Message::ToggleDirectory(node.path.clone()),
+            ),
+            false => (format!("{indent}  {}", node.name), Message::OpenTreeFile(node.path.clone())),
+        };
+
+        rows.push(
+            button(text(label).size(14))
+                .style(theme::Button::Text)
+                .width(Length::Fill)
+                .on_press(message))
+    });
+
+    let placeholder = if find_in_files.searching { "Searching..." } else { "Find in files" };
+    let find = text_input(placeholder, &find_in_files.query)
+        .on_input(Message::FindInFilesChanged)
+        .on_submit(Message::FindInFiles(find_in_files.query.clone()))
+        .size(14);
+
+    column![find, scrollable(rows).height(Length::Fill)].spacing(5).width(220).into()
+}
+
+/// id of the go-to-line input, so it can be focused when shown
+fn go_to_line_id() -> text_input::Id {
+    text_input::Id::new("go-to-line")
+}
+
+/// id of the command palette input, so it can be focused when shown
+fn palette_id() -> text_input::Id {
+    text_input::Id::new("command-palette")
+}
+
+/// turn a file dropped on the window into a message
+fn file_dropped(event: Event, _status: event::Status) -> Option<Message> {
+    match event {
+        Event::Window(window::Event::FileDropped(path)) => Some(Message::FileDropped(path)),
+        _ => None,
+    }
+}
+
+/// the window's close button, which we handle ourselves, and its size
+fn window_event(event: Event, _status: event::Status) -> Option<Message> {
+    match event {
+        Event::Window(window::Event::CloseRequested) => Some(Message::CloseRequested),
+        Event::Window(window::Event::Resized { height, .. }) => Some(Message::WindowResized(height)),
+        Event::Window(window::Event::Focused) => Some(Message::WindowFocused),
+        _ => None,
+    }
+}
+
+/// track held modifiers, whether or not a widget captured the event
+fn modifiers_changed(event: Event, _status: event::Status) -> Option<Message> {
+    match event {
+        Event::Keyboard(keyboard::Event::ModifiersChanged(modifiers)) => Some(Message::ModifiersChanged(modifiers)),
+        _ => None,
+    }
+}
+
+/// a key press no widget handled, for the keymap to look up
+fn key_pressed(event: Event, status: event::Status) -> Option<Message> {
+    match (event, status) {
+        (Event::Keyboard(keyboard::Event::KeyPressed { key_code, modifiers }), event::Status::Ignored) => {
+            Some(Message::KeyPressed(key_code, modifiers))
+        },
+        _ => None,
+    }
+}
+
+/// ctrl+c and ctrl+x, seen even when a widget handled them
+fn copied(event: Event, _status: event::Status) -> Option<Message> {
+    match event {
+        Event::Keyboard(keyboard::Event::KeyPressed { key_code: keyboard::KeyCode::C | keyboard::KeyCode::X, modifiers })
+            if modifiers.command() && !modifiers.alt() =>
+        {
+            Some(Message::Copied)
+        },
+        _ => None,
+    }
+}
+
+/// pick one or more files and read them all
+async fn pick_files(max_size: u64) -> Result<Vec<OpenedFile>, Error> {
+    let handles = rfd::AsyncFileDialog::new()
+        .set_title("Choose text files...")
+        .pick_files()
+        .await
+        .ok_or(Error::DialogClosed)?;
+
+    Ok(load_files(handles.iter().map(|handle| (handle.path().to_owned(), None)).collect(), max_size).await)
+}
+
+/// read several files at once, each one succeeding or failing on its own
+async fn load_files(locations: Vec<(PathBuf, Option<(usize, usize)>)>, max_size: u64) -> Vec<OpenedFile> {
+    let loads = locations.into_iter().map(|(path, position)| async move {
+        let file = load_file(path.clone(), max_size).await;
+        OpenedFile { path, position, file }
+    });
+
+    iced::futures::future::join_all(loads).await
+}
+ 
+/// add `delta` to every whole number in `text`, keeping zero-padded ones as wide
+///
+/// a `-` right before the digits is a sign unless it follows a letter or digit,
+/// so `x-1` stays a subtraction; decimals and numbers that would overflow are
+/// left as they are
+fn increment_numbers(text: &str, delta: i64) -> String {
+    let bytes = text.as_bytes();
+    let mut result = String::with_capacity(text.len());
+    let mut copied = 0;
+    let mut index = 0;
+
+    while index < bytes.len() {
+        if !bytes[index].is_ascii_digit() {
+            index += 1;
+            continue;
+        }
+
+        let digits_start = index;
+        while index < bytes.len() && bytes[index].is_ascii_digit() {
+            index += 1;
+        }
+        let digits = &text[digits_start..index];
+
+        let is_decimal = |at: usize, next: usize| bytes.get(at) == Some(&b'.') && bytes.get(next).is_some_and(u8::is_ascii_digit);
+        if is_decimal(index, index + 1) || (digits_start >= 2 && is_decimal(digits_start - 1, digits_start - 2)) {
+            continue;
+        }
+
+        let negative = digits_start >= 1
+            && bytes[digits_start - 1] == b'-'
+            && (digits_start < 2 || !bytes[digits_start - 2].is_ascii_alphanumeric());
+        let start = if negative { digits_start - 1 } else { digits_start };
+
+        let Some(value) = text[start..index].parse::<i64>().ok().and_then(|value| value.checked_add(delta)) else {
+            continue;
+        };
+
+        let width = if digits.len() > 1 && digits.starts_with('0') { digits.len() } else { 0 };
+        let sign = if value < 0 { "-" } else { "" };
+
+        result.push_str(&text[copied..start]);
+        result.push_str(&format!("{sign}{:0width$}", value.unsigned_abs()));
+        copied = index;
+    }
+
+    result.push_str(&text[copied..]);
+    result
+}
+
+/// capitalize the first letter of every word and lower the rest
+fn title_case(text: &str) -> String {
+    let mut title = String::with_capacity(text.len());
+    let mut word_start = true;
+
+    for c in text.chars() {
+        match word_start {
+            true => title.extend(c.to_uppercase()),
+            false => title.extend(c.to_lowercase()),
+        }
+        word_start = !c.is_alphanumeric() && c != '\'';
+    }
+
+    title
+}
+
+/// split `file:line[:col]` into the path and a 1-based position
+///
+/// only trailing numeric parts count, so `C:\foo` stays a path; a 0 is taken
+/// as 1, and numbers too big to hold as far as they go, for the cursor to be clamped
+fn parse_location(arg: &str) -> (PathBuf, Option<(usize, usize)>) {
+    let mut numbers = Vec::new();
+    let mut rest = arg;
+
+    while numbers.len() < 2 {
+        let Some((head, tail)) = rest.rsplit_once(':') else { break };
+        if tail.is_empty() || !tail.bytes().all(|byte| byte.is_ascii_digit()) {
+            break;
+        }
+        let number = tail.parse::<usize>().unwrap_or(usize::MAX).max(1);
+
+        // `C:12` is a drive-relative path on windows, not line 12 of `C`
+        let is_drive = head.len() == 1 && head.chars().all(|c| c.is_ascii_alphabetic());
+        if head.is_empty() || (cfg!(windows) && is_drive) {
+            break;
+        }
+
+        numbers.push(number);
+        rest = head;
+    }
+
+    let position = match numbers[..] {
+        [line] => Some((line, 1)),
+        [column, line] => Some((line, column)),
+        _ => None,
+    };
+
+    (PathBuf::from(rest), position)
+}
+
+/// ask whether unsaved changes should be saved first
+async fn ask_unsaved() -> Choice {
+    let result = rfd::AsyncMessageDialog::new()
+        .set_level(rfd::MessageLevel::Warning)
+        .set_title("Unsaved changes")
+        .set_description("Save your changes before continuing?")
+        .set_buttons(rfd::MessageButtons::YesNoCancel)
+        .show()
+        .await;
+
+    match result {
+        rfd::MessageDialogResult::Yes => Choice::Save,
+        rfd::MessageDialogResult::No => Choice::Discard,
+        _ => Choice::Cancel,
+    }
+}
+ 
+/// ask whether a file should be reloaded from disk
+async fn ask_reload(name: String, dirty: bool, external: bool) -> bool {
+    let question = if external {
+        format!("{name} changed on disk. Reload it?")
+    } else {
+        format!("Reload {name} from disk?")
+    };
+    let warning = if dirty { " Your unsaved changes will be lost." } else { "" };
+
+    let result = rfd::AsyncMessageDialog::new()
+        .set_level(rfd::MessageLevel::Warning)
+        .set_title("Reload file")
+        .set_description(format!("{question}{warning}"))
+        .set_buttons(rfd::MessageButtons::YesNo)
+        .show()
+        .await;
+
+    result == rfd::MessageDialogResult::Yes
+}
+
+/// ask whether a big file should be opened in the read-only mode
+async fn ask_light(name: String, size: u64) -> bool {
+    let result = rfd::AsyncMessageDialog::new()
+        .set_level(rfd::MessageLevel::Info)
+        .set_title("Large file")
+        .set_description(format!(
+            "{name} is {:.1} MB. Open it read-only to keep it responsive? \
+             It can be made editable later, which reads it again.",
+            size as f64 / (1024.0 * 1024.0),
+        ))
+        .set_buttons(rfd::MessageButtons::YesNo)
+        .show()
+        .await;
+
+    result == rfd::MessageDialogResult::Yes
+}
+
+/// ask before throwing away a buffer's unsaved changes
+async fn ask_revert(name: String, saved: bool) -> bool {
+    let what = match saved {
+        true => "go back to the saved file",
+        false => "clear it",
+    };
+
+    let result = rfd::AsyncMessageDialog::new()
+        .set_level(rfd::MessageLevel::Warning)
+        .set_title("Revert changes")
+        .set_description(format!("Discard all unsaved changes to {name} and {what}? This can't be undone."))
+        .set_buttons(rfd::MessageButtons::YesNo)
+        .show()
+        .await;
+
+    result == rfd::MessageDialogResult::Yes
+}
+
+async fn ask_restore(names: Vec<String>) -> bool {
+    let result = rfd::AsyncMessageDialog::new()
+        .set_level(rfd::MessageLevel::Warning)
+        .set_title("Restore unsaved changes")
+        .set_description(format!(
+            "Crab didn't close properly last time. Restore the unsaved changes to {}?",
+            names.join(", "),
+        ))
+        .set_buttons(rfd::MessageButtons::YesNo)
+        .show()
+        .await;
+
+    result == rfd::MessageDialogResult::Yes
+}
+
+/// file loader, refusing files over `max_size` bytes
+async fn load_file(path: PathBuf, max_size: u64) -> Result<LoadedFile, Error> {
+    // stat first so a huge file never gets read into memory
+    let metadata = match tokio::fs::metadata(&path).await {
+        Ok(metadata) => metadata,
+        // the link itself is there, what it points to isn't
+        Err(_) if tokio::fs::symlink_metadata(&path).await.is_ok_and(|link| link.is_symlink()) => {
+            return Err(Error::BrokenLink(path));
+        },
+        Err(error) => return Err(Error::io("open", &path)(error)),
+    };
+
+    if metadata.len() > max_size {
+        return Err(Error::FileTooLarge { size: metadata.len(), limit: max_size });
+    }
+
+    let bytes = tokio::fs::read(&path)
+        .await
+        .map_err(Error::io("open", &path))?;
+    let (content, encoding) = Encoding::decode(&bytes).ok_or(Error::UnknownEncoding)?;
+    let read_only = metadata.permissions().readonly();
+    let modified = metadata.modified().ok();
+
+    // links and `..` resolved, so one file always has one path in tabs and recents
+    let path = tokio::fs::canonicalize(&path)
+        .await
+        .map_err(Error::io("resolve the path of", &path))?;
+
+    // the editor works with \n lines, the ending is restored on save
+    let line_ending = LineEnding::detect(&content);
+    let content = Arc::new(content.replace("\r\n", "\n"));
+
+    let project = tree::project_root(&path).await;
+
+    Ok(LoadedFile { path, content, line_ending, encoding, read_only, modified, project })
+}
+
+/// the file read again, if it was modified since `since`
+async fn load_if_modified(path: PathBuf, since: Option<SystemTime>, max_size: u64) -> Option<Result<LoadedFile, Error>> {
+    let modified = tokio::fs::metadata(&path).await.ok()?.modified().ok();
+
+    if modified.is_some() && modified == since {
+        return None;
+    }
+
+    Some(load_file(path, max_size).await)
+}
+
+/// read a document piped to us, it has no path so saving asks for one
+async fn read_stdin(max_size: u64) -> Result<(Arc<String>, LineEnding, Encoding), Error> {
+    use std::io::IsTerminal;
+
+    // a terminal would wait for input nobody is going to type
+    if std::io::stdin().is_terminal() {
+        return Ok((Arc::default(), LineEnding::default(), Encoding::default()));
+    }
+
+    let error = Error::io("read", Path::new("standard input"));
+    let mut bytes = Vec::new();
+
+    tokio::io::stdin()
+        .take(max_size.saturating_add(1))
+        .read_to_end(&mut bytes)
+        .await
+        .map_err(error)?;
+
+    if bytes.len() as u64 > max_size {
+        return Err(Error::FileTooLarge { size: bytes.len() as u64, limit: max_size });
+    }
+
+    let (content, encoding) = Encoding::decode(&bytes).ok_or(Error::UnknownEncoding)?;
+    let line_ending = LineEnding::detect(&content);
+
+    Ok((Arc::new(content.replace("\r\n", "\n")), line_ending, encoding))
+}
+
+/// file saver, returning the path it wrote to
+async fn save_file(
+    path: Option<PathBuf>,
+    text: String,
+    line_ending: LineEnding,
+    encoding: Encoding,
+    backup_suffix: Option<String>,
+) -> Result<PathBuf, Error> {
+    // if we have a path we save to it, else we ask for a new path
+    let path = if let Some(path) = path { path } else {
+        rfd::AsyncFileDialog::new()
+            .set_title("Choose a file name...")
+            .save_file()
+            .await
+            .ok_or(Error::DialogClosed)
+            .map(|handle| handle.path().to_owned())?
+    };
+
+    check_target(&path).await?;
+
+    // no backup means no save, the original must not be lost
+    if let Some(suffix) = backup_suffix.filter(|suffix| !suffix.is_empty()) {
+        if tokio::fs::try_exists(&path).await.unwrap_or(false) {
+            let mut backup = path.clone().into_os_string();
+            backup.push(suffix);
+
+            tokio::fs::copy(&path, &backup)
+                .await
+                .map_err(Error::io("back up", &path))?;
+        }
+    }
+
+    write_atomic(&path, &encoding.encode(&line_ending.apply(&text)))
+        .await
+        .map_err(Error::io("save", &path))?;
+
+    Ok(path)
+}
+
+/// whether `path` can be saved to, apart from the dialog so it can be tested
+async fn check_target(path: &Path) -> Result<(), Error> {
+    // writing to a directory fails with an unhelpful io error, so catch it first
+    match tokio::fs::metadata(path).await {
+        Ok(metadata) if metadata.is_dir() => Err(Error::IsDirectory(path.to_owned())),
+        _ => Ok(()),
+    }
+}
+
+/// write to a temporary file next to `path` and rename it over, so a crash
+/// mid-write leaves either the old file or the new one, never half of each
+async fn write_atomic(path: &Path, contents: &[u8]) -> io::Result<()> {
+    // replace what a symlink points to, not the link itself
+    let path = tokio::fs::canonicalize(path).await.unwrap_or_else(|_| path.to_owned());
+    let name = path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+    let temp = path.with_file_name(format!(".{name}.{}.tmp", std::process::id()));
+
+    let written = async {
+        tokio::fs::write(&temp, contents).await?;
+
+        // the new file should keep the permissions of the one it replaces
+        if let Ok(metadata) = tokio::fs::metadata(&path).await {
+            tokio::fs::set_permissions(&temp, metadata.permissions()).await?;
+        }
+
+        match tokio::fs::rename(&temp, &path).await {
+            // the same directory can still be on another mount, bind mounts do that
+            Err(error) if crosses_devices(&error) => {
+                tokio::fs::copy(&temp, &path).await?;
+                tokio::fs::remove_file(&temp).await
+            },
+            result => result,
+        }
+    }
+    .await;
+
+    if written.is_err() {
+        let _ = tokio::fs::remove_file(&temp).await;
+    }
+
+    written
+}
+
+/// whether a rename failed only because it crossed filesystems
+fn crosses_devices(error: &io::Error) -> bool {
+    // EXDEV on unix, ERROR_NOT_SAME_DEVICE on windows
+    let code = if cfg!(windows) { 17 } else { 18 };
+    error.raw_os_error() == Some(code)
+}
+
+#[derive(Debug, Clone)]
+enum Error {
+    DialogClosed, // the user cancelled, handled quietly and never shown
+    IO { action: &'static str, path: PathBuf, kind: io::ErrorKind },
+    UnknownEncoding,
+    FileTooLarge { size: u64, limit: u64 }, // in bytes
+    IsDirectory(PathBuf), // picked as a save target
+    BrokenLink(PathBuf),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        const MB: f64 = 1024.0 * 1024.0;
+
+        match self {
+            Error::DialogClosed => write!(f, "The dialog was closed without choosing a file"),
+            Error::IO { action, path, kind } => write!(f, "Failed to {action} {}: {}", path.display(), describe(*kind)),
+            Error::UnknownEncoding => write!(f, "Can't open: the file is not UTF-8, UTF-16 or Latin-1 text"),
+            Error::FileTooLarge { size, limit } => write!(
+                f,
+                "Can't open: the file is {:.1} MB, over the {:.0} MB limit",
+                *size as f64 / MB,
+                *limit as f64 / MB,
+            ),
+            Error::IsDirectory(path) => write!(f, "Cannot save: {} is a directory", path.display()),
+            Error::BrokenLink(path) => write!(f, "Can't open: {} links to a file that doesn't exist", path.display()),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl Error {
+    /// map an `io::Error` from `action` on `path`
+    fn io(action: &'static str, path: &Path) -> impl FnOnce(io::Error) -> Error {
+        let path = path.to_owned();
+        move |error| Error::IO { action, path, kind: error.kind() }
+    }
+}
+
+/// open the platform's file browser at `dir`
+async fn reveal(dir: PathBuf) -> Result<(), Error> {
+    let program = if cfg!(target_os = "windows") {
+        "explorer"
+    } else if cfg!(target_os = "macos") {
+        "open"
+    } else {
+        "xdg-open"
+    };
+
+    let status = tokio::process::Command::new(program)
+        .arg(&dir)
+        .status()
+        .await
+        .map_err(Error::io("open a file manager at", &dir))?;
+
+    // explorer exits with 1 even when it worked
+    if !status.success() && !cfg!(target_os = "windows") {
+        return Err(Error::IO { action: "open a file manager at", path: dir, kind: io::ErrorKind::Other });
+    }
+
+    Ok(())
+}
+
+/// friendlier wording for the common io errors
+fn describe(kind: io::ErrorKind) -> String {
+    match kind {
+        io::ErrorKind::NotFound => String::from("file not found"),
+        io::ErrorKind::PermissionDenied => String::from("permission denied"),
+        io::ErrorKind::AlreadyExists => String::from("file already exists"),
+        io::ErrorKind::InvalidData => String::from("file contents are invalid"),
+        io::ErrorKind::OutOfMemory => String::from("out of memory"),
+        kind => kind.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn block_on<F: std::future::Future>(future: F) -> F::Output {
+        tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap().block_on(future)
+    }
+
+    #[test]
+    fn saving_over_a_directory_fails() {
+        let dir = std::env::temp_dir().join(format!("crab-save-target-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let result = block_on(check_target(&dir));
+        let saved = block_on(save_file(Some(dir.clone()), String::from("text"), LineEnding::Lf, Encoding::Utf8, None));
+        std::fs::remove_dir(&dir).unwrap();
+
+        assert!(matches!(result, Err(Error::IsDirectory(path)) if path == dir));
+        assert!(matches!(saved, Err(Error::IsDirectory(path)) if path == dir));
+    }
+
+    #[test]
+    fn saving_to_a_new_file_is_fine() {
+        let file = std::env::temp_dir().join(format!("crab-save-target-{}.txt", std::process::id()));
+
+        assert!(block_on(check_target(&file)).is_ok());
+    }
+
+    #[test]
+    fn increment_numbers_with_a_sign() {
+        assert_eq!(increment_numbers("-1", 1), "0");
+        assert_eq!(increment_numbers("0", -1), "-1");
+        assert_eq!(increment_numbers("(-5)", 2), "(-3)");
+
+        // after a letter or digit the dash is a minus, not a sign
+        assert_eq!(increment_numbers("x-1", 1), "x-2");
+        assert_eq!(increment_numbers("3-1", 1), "4-2");
+    }
+
+    #[test]
+    fn increment_numbers_keeps_zero_padding() {
+        assert_eq!(increment_numbers("007", 1), "008");
+        assert_eq!(increment_numbers("009", 1), "010");
+        assert_eq!(increment_numbers("099", 1), "100");
+        assert_eq!(increment_numbers("001", -2), "-001");
+
+        // a lone zero isn't padding
+        assert_eq!(increment_numbers("0", 10), "10");
+    }
+
+    #[test]
+    fn increment_numbers_leaves_overflowing_numbers() {
+        assert_eq!(increment_numbers("9223372036854775807", 1), "9223372036854775807");
+        assert_eq!(increment_numbers("-9223372036854775808", -1), "-9223372036854775808");
+        assert_eq!(increment_numbers("-9223372036854775808", 1), "-9223372036854775807");
+        assert_eq!(increment_numbers("9223372036854775806", 1), "9223372036854775807");
+
+        // too big to read in the first place
+        assert_eq!(increment_numbers("99999999999999999999", -1), "99999999999999999999");
+    }
+
+    #[test]
+    fn increment_numbers_leaves_decimals() {
+        assert_eq!(increment_numbers("1.5", 1), "1.5");
+        assert_eq!(increment_numbers("-0.25", 1), "-0.25");
+
+        // a full stop that isn't a decimal point
+        assert_eq!(increment_numbers("item 1.", 1), "item 2.");
+        assert_eq!(increment_numbers("v.2", 1), "v.3");
+    }
+
+    #[test]
+    fn increment_numbers_changes_every_number() {
+        assert_eq!(increment_numbers("a 1 b 22 c -3", 1), "a 2 b 23 c -2");
+        assert_eq!(increment_numbers("[1, 2, 3]", -1), "[0, 1, 2]");
+        assert_eq!(increment_numbers("line 9\nline 10", 1), "line 10\nline 11");
+    }
+
+    #[test]
+    fn ctrl_arrow_moves_when_there_are_no_numbers() {
+        // `changes_numbers` guards the ctrl+up/down arms this way, an unchanged
+        // selection falls through to moving the cursor
+        for selection in ["", "abc", "1.5", "x-y"] {
+            assert_eq!(increment_numbers(selection, 1), selection);
+            assert_eq!(increment_numbers(selection, -1), selection);
+        }
+    }
+
+    #[test]
+    fn parse_location_after_a_drive_letter() {
+        assert_eq!(parse_location("C:\\a.rs:3"), (PathBuf::from("C:\\a.rs"), Some((3, 1))));
+        assert_eq!(parse_location("C:\\a.rs:3:7"), (PathBuf::from("C:\\a.rs"), Some((3, 7))));
+        assert_eq!(parse_location("C:\\a.rs"), (PathBuf::from("C:\\a.rs"), None));
+    }
+
+    #[test]
+    fn parse_location_keeps_colons_in_the_path() {
+        assert_eq!(parse_location("a:b.txt"), (PathBuf::from("a:b.txt"), None));
+        assert_eq!(parse_location("a:b.txt:4"), (PathBuf::from("a:b.txt"), Some((4, 1))));
+        assert_eq!(parse_location("a.rs:"), (PathBuf::from("a.rs:"), None));
+        assert_eq!(parse_location(":3"), (PathBuf::from(":3"), None));
+        assert_eq!(parse_location("a.rs:+3"), (PathBuf::from("a.rs:+3"), None));
+    }
+
+    #[test]
+    fn parse_location_line_zero_is_the_first() {
+        assert_eq!(parse_location("a.rs:0"), (PathBuf::from("a.rs"), Some((1, 1))));
+        assert_eq!(parse_location("a.rs:0:0"), (PathBuf::from("a.rs"), Some((1, 1))));
+    }
+
+    #[test]
+    fn parse_location_with_a_huge_column() {
+        assert_eq!(parse_location("a.rs:3:99999999999999999999999"), (PathBuf::from("a.rs"), Some((3, usize::MAX))));
+        assert_eq!(parse_location("a.rs:3:500"), (PathBuf::from("a.rs"), Some((3, 500))));
+    }
+
+    #[test]
+    fn ctrl_s_key_press_saves() {
+        let press = Event::Keyboard(keyboard::Event::KeyPressed {
+            key_code: keyboard::KeyCode::S,
+            modifiers: keyboard::Modifiers::COMMAND,
+        });
+        let keymap = keymap::Keymap::new(&std::collections::BTreeMap::new());
+
+        // the subscription's message, looked up the way update does
+        let Some(Message::KeyPressed(key_code, modifiers)) = key_pressed(press, event::Status::Ignored) else {
+            panic!("the key press wasn't turned into a message");
+        };
+        assert!(matches!(keymap.get(key_code, modifiers), Some(Message::Save)));
+    }
+
+    #[test]
+    fn key_presses_a_widget_handled_are_left_alone() {
+        let press = Event::Keyboard(keyboard::Event::KeyPressed {
+            key_code: keyboard::KeyCode::S,
+            modifiers: keyboard::Modifiers::COMMAND,
+        });
+        let release = Event::Keyboard(keyboard::Event::KeyReleased {
+            key_code: keyboard::KeyCode::S,
+            modifiers: keyboard::Modifiers::COMMAND,
+        });
+
+        assert!(key_pressed(press, event::Status::Captured).is_none());
+        assert!(key_pressed(release, event::Status::Ignored).is_none());
+    }
 }
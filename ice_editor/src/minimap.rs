@@ -0,0 +1,117 @@
+use std::sync::Arc;
+use iced::{
+    mouse,
+    widget::canvas::{self, event, Frame, Geometry},
+    Color, Point, Rectangle, Renderer, Size, Theme,
+};
+
+use crate::Message;
+
+/// most pixels a line gets, so short files don't turn into thick stripes
+const MAX_ROW: f32 = 3.0;
+/// lines are scaled to fit at least this many columns
+const MIN_COLUMNS: usize = 80;
+
+/// a scaled-down outline of the buffer, with a band around the cursor
+///
+/// the editor doesn't expose how far it's scrolled, so the band follows the
+/// cursor; dragging it moves the cursor, which scrolls the editor along
+pub struct Minimap {
+    pub lines: Arc<Vec<(usize, usize)>>, // indent and length of every line
+    pub cursor_line: usize,
+    pub visible_lines: usize, // rough height of the editor, in lines
+}
+
+/// whether the band is being dragged
+#[derive(Debug, Default)]
+pub struct State {
+    dragging: bool,
+}
+
+impl Minimap {
+    /// pixels per line in `bounds`
+    fn row(&self, bounds: Rectangle) -> f32 {
+        (bounds.height / self.lines.len().max(1) as f32).min(MAX_ROW)
+    }
+
+    /// the line drawn at `y`, clamped to the buffer
+    fn line_at(&self, y: f32, bounds: Rectangle) -> usize {
+        let line = ((y - bounds.y).max(0.0) / self.row(bounds)) as usize;
+        line.min(self.lines.len().saturating_sub(1))
+    }
+}
+
+impl canvas::Program<Message> for Minimap {
+    type State = State;
+
+    fn update(
+        &self,
+        state: &mut State,
+        event: canvas::Event,
+        bounds: Rectangle,
+        cursor: mouse::Cursor,
+    ) -> (event::Status, Option<Message>) {
+        let jump = |position: Point| Some(Message::MinimapJump(self.line_at(position.y, bounds)));
+
+        match event {
+            canvas::Event::Mouse(mouse::Event::ButtonPressed(mouse::Button::Left)) => match cursor.position_over(bounds) {
+                Some(position) => {
+                    state.dragging = true;
+                    (event::Status::Captured, jump(position))
+                },
+                None => (event::Status::Ignored, None),
+            },
+            // keep following the mouse even when it leaves the minimap
+            canvas::Event::Mouse(mouse::Event::CursorMoved { position }) if state.dragging => {
+                (event::Status::Captured, jump(position))
+            },
+            canvas::Event::Mouse(mouse::Event::ButtonReleased(mouse::Button::Left)) if state.dragging => {
+                state.dragging = false;
+                (event::Status::Captured, None)
+            },
+            _ => (event::Status::Ignored, None),
+        }
+    }
+
+    fn draw(
+        &self,
+        _state: &State,
+        renderer: &Renderer,
+        theme: &Theme,
+        bounds: Rectangle,
+        _cursor: mouse::Cursor,
+    ) -> Vec<Geometry> {
+        let mut frame = Frame::new(renderer, bounds.size());
+        let palette = theme.palette();
+
+        let row = self.row(bounds);
+        let longest = self.lines.iter().map(|(indent, length)| indent + length).max().unwrap_or(0);
+        let column = bounds.width / longest.max(MIN_COLUMNS) as f32;
+        let ink = Color { a: 0.35, ..palette.text };
+
+        // with more lines than pixels, every pixel row shows the line it lands on
+        let rows = (self.lines.len() as f32 * row).ceil() as usize;
+        let step = (row.max(1.0)) as usize;
+
+        for y in (0..rows).step_by(step) {
+            let (indent, length) = self.lines[((y as f32 / row) as usize).min(self.lines.len() - 1)];
+
+            if length > 0 {
+                frame.fill_rectangle(
+                    Point::new(indent as f32 * column, y as f32),
+                    Size::new(length as f32 * column, (row - 0.5).max(1.0)),
+                    ink,
+                );
+            }
+        }
+
+        let start = self.cursor_line.saturating_sub(self.visible_lines / 2);
+        frame.fill_rectangle(
+            Point::new(0.0, start as f32 * row),
+            Size::new(bounds.width, (self.visible_lines as f32 * row).max(4.0)),
+            Color { a: 0.2, ..palette.primary },
+        );
+
+        vec![frame.into_geometry()]
+    }
+}
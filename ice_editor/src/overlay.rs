@@ -0,0 +1,191 @@
+use std::sync::Arc;
+use iced::{
+    advanced::{
+        layout, renderer,
+        widget::{Operation, Tree},
+        Clipboard, Layout, Renderer as _, Shell, Widget,
+    },
+    event, mouse, Color, Element, Event, Length, Rectangle, Renderer, Theme,
+};
+
+use crate::{Message, CHAR_WIDTH, LINE_HEIGHT};
+
+/// the editor's padding, the text starts this far in
+const PADDING: f32 = 5.0;
+/// how much of the text color an indentation guide gets
+const GUIDE_ALPHA: f32 = 0.15;
+/// rulers stand out a little more than guides
+const RULER_ALPHA: f32 = 0.3;
+
+/// the editor with lines drawn over it: a ruler at each of some columns, and
+/// optionally a guide at every indentation level
+pub struct Overlay<'a> {
+    content: Element<'a, Message>,
+    font_size: f32,
+    rulers: Vec<usize>, // columns, counted from 0
+    guides: Option<Guides>,
+}
+
+/// what the indentation guides are drawn from
+///
+/// lines are placed by their number, so guides only line up while the editor
+/// isn't wrapping and grows to fit its text inside an outer scrollable
+struct Guides {
+    lines: Arc<Vec<(usize, usize)>>, // indent and length of each line
+    tabs: Arc<Vec<usize>>,           // how much of each indent is tabs
+    tab_width: usize,
+}
+
+impl<'a> Overlay<'a> {
+    pub fn new(content: impl Into<Element<'a, Message>>, font_size: f32) -> Self {
+        Self { content: content.into(), font_size, rulers: Vec::new(), guides: None }
+    }
+
+    pub fn rulers(self, rulers: Vec<usize>) -> Self {
+        Self { rulers, ..self }
+    }
+
+    pub fn guides(self, lines: Arc<Vec<(usize, usize)>>, tabs: Arc<Vec<usize>>, tab_width: usize) -> Self {
+        Self { guides: Some(Guides { lines, tabs, tab_width: tab_width.max(1) }), ..self }
+    }
+
+    /// a thin line in `color`
+    fn line(renderer: &mut Renderer, bounds: Rectangle, color: Color) {
+        renderer.fill_quad(
+            renderer::Quad {
+                bounds,
+                border_radius: 0.0.into(),
+                border_width: 0.0,
+                border_color: Color::TRANSPARENT,
+            },
+            color,
+        );
+    }
+}
+
+impl Guides {
+    /// how many guides `line` gets, blank lines carry on the ones around them
+    fn level(&self, line: usize) -> usize {
+        let indent = |line: usize| {
+            let (indent, _) = self.lines[line];
+            let tabs = self.tabs.get(line).copied().unwrap_or(0);
+            (indent - tabs + tabs * self.tab_width) / self.tab_width
+        };
+
+        if self.lines[line].1 > 0 {
+            return indent(line);
+        }
+
+        let above = (0..line).rev().find(|&line| self.lines[line].1 > 0).map_or(0, indent);
+        let below = (line + 1..self.lines.len()).find(|&line| self.lines[line].1 > 0).map_or(0, indent);
+        above.min(below)
+    }
+}
+
+impl Widget<Message, Renderer> for Overlay<'_> {
+    fn width(&self) -> Length {
+        self.content.as_widget().width()
+    }
+
+    fn height(&self) -> Length {
+        self.content.as_widget().height()
+    }
+
+    fn children(&self) -> Vec<Tree> {
+        vec![Tree::new(&self.content)]
+    }
+
+    fn diff(&self, tree: &mut Tree) {
+        tree.diff_children(std::slice::from_ref(&self.content));
+    }
+
+    fn layout(&self, tree: &mut Tree, renderer: &Renderer, limits: &layout::Limits) -> layout::Node {
+        self.content.as_widget().layout(&mut tree.children[0], renderer, limits)
+    }
+
+    fn operate(&self, tree: &mut Tree, layout: Layout<'_>, renderer: &Renderer, operation: &mut dyn Operation<Message>) {
+        self.content.as_widget().operate(&mut tree.children[0], layout, renderer, operation);
+    }
+
+    fn on_event(
+        &mut self,
+        tree: &mut Tree,
+        event: Event,
+        layout: Layout<'_>,
+        cursor: mouse::Cursor,
+        renderer: &Renderer,
+        clipboard: &mut dyn Clipboard,
+        shell: &mut Shell<'_, Message>,
+        viewport: &Rectangle,
+    ) -> event::Status {
+        self.content.as_widget_mut().on_event(
+            &mut tree.children[0], event, layout, cursor, renderer, clipboard, shell, viewport,
+        )
+    }
+
+    fn mouse_interaction(
+        &self,
+        tree: &Tree,
+        layout: Layout<'_>,
+        cursor: mouse::Cursor,
+        viewport: &Rectangle,
+        renderer: &Renderer,
+    ) -> mouse::Interaction {
+        self.content.as_widget().mouse_interaction(&tree.children[0], layout, cursor, viewport, renderer)
+    }
+
+    fn draw(
+        &self,
+        tree: &Tree,
+        renderer: &mut Renderer,
+        theme: &Theme,
+        style: &renderer::Style,
+        layout: Layout<'_>,
+        cursor: mouse::Cursor,
+        viewport: &Rectangle,
+    ) {
+        self.content.as_widget().draw(&tree.children[0], renderer, theme, style, layout, cursor, viewport);
+
+        let bounds = layout.bounds();
+        let Some(visible) = bounds.intersection(viewport) else {
+            return;
+        };
+
+        let char_width = self.font_size * CHAR_WIDTH;
+        let mut color = theme.palette().text;
+
+        color.a = RULER_ALPHA;
+        for column in &self.rulers {
+            let x = bounds.x + PADDING + *column as f32 * char_width;
+            Self::line(renderer, Rectangle { x, y: visible.y, width: 1.0, height: visible.height }, color);
+        }
+
+        let Some(guides) = &self.guides else {
+            return;
+        };
+
+        let line_height = self.font_size * LINE_HEIGHT;
+        let indent = char_width * guides.tab_width as f32;
+        color.a = GUIDE_ALPHA;
+
+        // only the lines in view are drawn
+        let first = ((visible.y - bounds.y - PADDING) / line_height).max(0.0) as usize;
+        let last = (((visible.y + visible.height - bounds.y - PADDING) / line_height).ceil().max(0.0) as usize)
+            .min(guides.lines.len());
+
+        for line in first..last {
+            let y = bounds.y + PADDING + line as f32 * line_height;
+
+            for level in 0..guides.level(line) {
+                let x = bounds.x + PADDING + level as f32 * indent;
+                Self::line(renderer, Rectangle { x, y, width: 1.0, height: line_height }, color);
+            }
+        }
+    }
+}
+
+impl<'a> From<Overlay<'a>> for Element<'a, Message> {
+    fn from(overlay: Overlay<'a>) -> Self {
+        Element::new(overlay)
+    }
+}
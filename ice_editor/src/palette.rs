@@ -0,0 +1,144 @@
+use crate::{buffer::Buffer, highlight::Language, Message};
+
+/// state of the command palette
+#[derive(Debug, Clone, Default)]
+pub struct Palette {
+    pub query: String,
+    pub scripts: Vec<String>, // the user's scripts, listed when the palette opens
+}
+
+/// a named action the palette can run
+#[derive(Debug, Clone)]
+pub struct Entry {
+    pub id: &'static str, // stable name for keybindings
+    pub name: &'static str,
+    pub message: Message,
+    pub available: fn(&Buffer) -> bool, // whether it makes sense for the active buffer
+}
+
+/// every command the palette offers, new ones only need a line here
+pub fn commands() -> Vec<Entry> {
+    let entry = |id, name, message| Entry { id, name, message, available: |_: &Buffer| true };
+    // only offered once the buffer is saved somewhere
+    let file_entry = |id, name, message| Entry { id, name, message, available: |buffer: &Buffer| buffer.path.is_some() };
+    let sort = |ascending, case_insensitive, unique| Message::SortLines { ascending, case_insensitive, unique };
+
+    vec![
+        entry("palette", "Command Palette", Message::TogglePalette),
+        entry("new", "New File", Message::New),
+        entry("new_scratch", "New Scratch Buffer", Message::NewScratch),
+        entry("new_window", "New Window", Message::NewWindow),
+        entry("open", "Open File", Message::Open),
+        entry("reopen_closed_tab", "Reopen Closed Tab", Message::ReopenClosed),
+        entry("open_folder", "Open Folder", Message::OpenFolder),
+        file_entry("open_project", "Open Project Folder", Message::OpenProject),
+        entry("save", "Save", Message::Save),
+        entry("save_as", "Save As", Message::SaveAs),
+        entry("reload", "Reload From Disk", Message::Reload),
+        entry("revert", "Revert All Changes", Message::Revert),
+        file_entry("pin_file", "Pin File", Message::PinFile),
+        file_entry("show_diff", "Show Changes Since Save", Message::ShowDiff),
+        file_entry("run_external", "Run External Command", Message::RunExternal),
+        file_entry("copy_path", "Copy Path", Message::CopyPath { relative: false }),
+        file_entry("copy_relative_path", "Copy Relative Path", Message::CopyPath { relative: true }),
+        file_entry("reveal_in_file_manager", "Reveal in File Manager", Message::RevealInFileManager),
+        entry("undo", "Undo", Message::Undo),
+        entry("redo", "Redo", Message::Redo),
+        entry("paste_from_history", "Paste From Clipboard History", Message::PasteFromHistory),
+        entry("clear_clipboard_history", "Clear Clipboard History", Message::ClearClipboardHistory),
+        entry("duplicate_line", "Duplicate Line", Message::DuplicateLine),
+        entry("add_next_occurrence", "Add Cursor at Next Occurrence", Message::AddNextOccurrence),
+        entry("extend_block_up", "Extend Block Selection Up", Message::ExtendBlock { down: false }),
+        entry("extend_block_down", "Extend Block Selection Down", Message::ExtendBlock { down: true }),
+        entry("select_line", "Select Line", Message::SelectLine),
+        entry("select_all", "Select All", Message::SelectAll),
+        entry("delete_line", "Delete Line", Message::DeleteLine),
+        entry("toggle_comment", "Toggle Comment", Message::ToggleComment),
+        entry("insert_date_time", "Insert Date/Time", Message::InsertDateTime),
+        entry("upper_case", "Upper Case", Message::UpperCase),
+        entry("lower_case", "Lower Case", Message::LowerCase),
+        entry("title_case", "Title Case", Message::TitleCase),
+        entry("increment_numbers", "Increment Numbers", Message::IncrementNumbers(1)),
+        entry("decrement_numbers", "Decrement Numbers", Message::IncrementNumbers(-1)),
+        entry("crop_to_selection", "Crop to Selection", Message::CropToSelection),
+        entry("sort_lines", "Sort Lines", sort(true, false, false)),
+        entry("sort_lines_descending", "Sort Lines Descending", sort(false, false, false)),
+        entry("sort_lines_case_insensitive", "Sort Lines Ignoring Case", sort(true, true, false)),
+        entry("sort_lines_unique", "Sort Lines Removing Duplicates", sort(true, false, true)),
+        entry("move_line_up", "Move Line Up", Message::MoveLineUp),
+        entry("move_line_down", "Move Line Down", Message::MoveLineDown),
+        entry("find", "Find and Replace", Message::ToggleSearch),
+        entry("go_to_line", "Go To Line", Message::ToggleGoToLine),
+        entry("jump_to_bracket", "Go To Matching Bracket", Message::JumpToBracket),
+        entry("toggle_theme", "Toggle Theme", Message::ToggleTheme),
+        entry("toggle_line_numbers", "Toggle Line Numbers", Message::ToggleLineNumbers),
+        entry("toggle_word_wrap", "Toggle Word Wrap", Message::ToggleWrap),
+        entry("show_effective_settings", "Show Effective Settings", Message::ShowEffectiveSettings),
+        entry("toggle_split_view", "Toggle Split View", Message::ToggleSplit),
+        entry("toggle_zen", "Toggle Zen Mode", Message::ToggleZen),
+        entry("toggle_minimap", "Toggle Minimap", Message::ToggleMinimap),
+        entry("toggle_indent_guides", "Toggle Indentation Guides", Message::ToggleIndentGuides),
+        entry("toggle_rulers", "Toggle Rulers", Message::ToggleRulers),
+        entry("toggle_scroll_past_end", "Toggle Scroll Past End", Message::ToggleScrollPastEnd),
+        Entry {
+            id: "toggle_preview",
+            name: "Toggle Markdown Preview",
+            message: Message::TogglePreview,
+            available: |buffer| buffer.language() == Language::Markdown,
+        },
+        entry("toggle_whitespace", "Toggle Show Whitespace", Message::ToggleShowWhitespace),
+        entry("toggle_read_only", "Toggle Read-Only", Message::ToggleReadOnly),
+        entry("zoom_in", "Zoom In", Message::ZoomIn),
+        entry("zoom_out", "Zoom Out", Message::ZoomOut),
+        entry("reset_zoom", "Reset Zoom", Message::ResetZoom),
+    ]
+}
+
+/// the message of the command called `id`
+pub fn command(id: &str) -> Option<Message> {
+    commands().into_iter().find(|entry| entry.id == id).map(|entry| entry.message)
+}
+
+/// commands matching `query`, best first, leaving out the ones that don't
+/// apply to `buffer`
+pub fn filter(query: &str, buffer: &Buffer) -> Vec<Entry> {
+    let mut matches: Vec<_> = commands()
+        .into_iter()
+        .filter(|entry| (entry.available)(buffer))
+        .filter_map(|entry| score(query, entry.name).map(|score| (score, entry)))
+        .collect();
+
+    matches.sort_by_key(|(score, _)| *score); // stable, so ties keep registry order
+    matches.into_iter().map(|(_, entry)| entry).collect()
+}
+
+/// the user's scripts matching `query`, best first, as the name shown and
+/// the message running it
+pub fn filter_scripts(query: &str, scripts: &[String]) -> Vec<(String, Message)> {
+    let mut matches: Vec<_> = scripts
+        .iter()
+        .map(|script| (format!("Run Script: {script}"), Message::RunScript(script.clone())))
+        .filter_map(|(name, message)| score(query, &name).map(|score| (score, (name, message))))
+        .collect();
+
+    matches.sort_by_key(|(score, _)| *score);
+    matches.into_iter().map(|(_, script)| script).collect()
+}
+
+/// fuzzy match: every query char must appear in order, fewer skipped chars rank higher
+fn score(query: &str, name: &str) -> Option<usize> {
+    let mut name = name.chars().map(|c| c.to_ascii_lowercase());
+    let mut skipped = 0;
+
+    for wanted in query.chars().filter(|c| !c.is_whitespace()).map(|c| c.to_ascii_lowercase()) {
+        loop {
+            match name.next() {
+                Some(c) if c == wanted => break,
+                Some(_) => skipped += 1,
+                None => return None,
+            }
+        }
+    }
+
+    Some(skipped)
+}
@@ -0,0 +1,163 @@
+use iced::{
+    widget::{column, container, horizontal_rule, row, scrollable, text, Space},
+    Element, Font, Length,
+};
+use pulldown_cmark::{Event, Options, Parser, Tag};
+
+use crate::Message;
+
+/// the rendered side panel for a Markdown buffer
+#[derive(Debug, Clone, Default)]
+pub struct Preview {
+    pub blocks: Vec<Block>,
+    pub rendered: Option<(usize, usize)>, // buffer id and revision the blocks show
+    pub pending: Option<(usize, usize)>,  // being rendered right now
+}
+
+/// a block of rendered Markdown
+///
+/// text widgets have no spans, so inline formatting is flattened
+#[derive(Debug, Clone)]
+pub enum Block {
+    Heading(usize, String), // level 1 to 6
+    Paragraph { text: String, quoted: bool },
+    Item { depth: usize, marker: String, text: String },
+    Code(String),
+    Rule,
+}
+
+/// parse `markdown` into blocks
+///
+/// it runs as a command, on the executor's threads, so a long document
+/// doesn't hold up the UI
+pub async fn render(markdown: String) -> Vec<Block> {
+    let mut builder = Builder::default();
+    let options = Options::ENABLE_TABLES | Options::ENABLE_STRIKETHROUGH | Options::ENABLE_TASKLISTS;
+
+    for event in Parser::new_ext(&markdown, options) {
+        builder.event(event);
+    }
+
+    builder.flush();
+    builder.blocks
+}
+
+/// collects inline text until the block it belongs to ends
+#[derive(Default)]
+struct Builder {
+    blocks: Vec<Block>,
+    text: String,
+    lists: Vec<Option<u64>>, // next number of each open list, `None` for bullets
+    marker: Option<String>,  // of the item whose text is being collected
+    quotes: usize,
+    code: Option<String>,
+}
+
+impl Builder {
+    fn event(&mut self, event: Event<'_>) {
+        match event {
+            Event::Start(Tag::Heading(..) | Tag::Paragraph | Tag::TableHead | Tag::TableRow) => self.flush(),
+            Event::End(Tag::Heading(level, ..)) => {
+                let text = std::mem::take(&mut self.text).trim().to_owned();
+                self.blocks.push(Block::Heading(level as usize, text));
+            },
+            Event::End(Tag::Paragraph | Tag::Item | Tag::TableHead | Tag::TableRow) => self.flush(),
+            Event::End(Tag::TableCell) => self.text.push_str("   "),
+            Event::Start(Tag::List(start)) => {
+                self.flush();
+                self.lists.push(start);
+            },
+            Event::End(Tag::List(_)) => {
+                self.flush();
+                self.lists.pop();
+            },
+            Event::Start(Tag::Item) => {
+                self.flush();
+                self.marker = Some(match self.lists.last_mut() {
+                    Some(Some(number)) => {
+                        *number += 1;
+                        format!("{}.", *number - 1)
+                    },
+                    _ => String::from("•"),
+                });
+            },
+            Event::Start(Tag::BlockQuote) => {
+                self.flush();
+                self.quotes += 1;
+            },
+            Event::End(Tag::BlockQuote) => {
+                self.flush();
+                self.quotes = self.quotes.saturating_sub(1);
+            },
+            Event::Start(Tag::CodeBlock(_)) => {
+                self.flush();
+                self.code = Some(String::new());
+            },
+            Event::End(Tag::CodeBlock(_)) => {
+                let code = self.code.take().unwrap_or_default();
+                self.blocks.push(Block::Code(code.trim_end_matches('\n').to_owned()));
+            },
+            Event::Text(text) => match &mut self.code {
+                Some(code) => code.push_str(&text),
+                None => self.text.push_str(&text),
+            },
+            Event::Code(code) => self.text.push_str(&code),
+            Event::SoftBreak => self.text.push(' '),
+            Event::HardBreak => self.text.push('\n'),
+            Event::TaskListMarker(done) => self.text.push_str(if done { "☑ " } else { "☐ " }),
+            Event::Rule => {
+                self.flush();
+                self.blocks.push(Block::Rule);
+            },
+            _ => {},
+        }
+    }
+
+    /// end the block the collected text belongs to
+    fn flush(&mut self) {
+        let text = std::mem::take(&mut self.text).trim().to_owned();
+        if text.is_empty() {
+            return;
+        }
+
+        let depth = self.lists.len().saturating_sub(1);
+        self.blocks.push(match (self.marker.take(), self.lists.is_empty()) {
+            (Some(marker), _) => Block::Item { depth, marker, text },
+            // a later paragraph of the same item
+            (None, false) => Block::Item { depth, marker: String::new(), text },
+            (None, true) => Block::Paragraph { text, quoted: self.quotes > 0 },
+        });
+    }
+}
+
+impl Preview {
+    /// the blocks stacked in a scrollable, scaled to the editor's `font_size`
+    pub fn view(&self, font_size: f32) -> Element<'_, Message> {
+        let blocks = self.blocks.iter().fold(column![].spacing(8), |blocks, block| {
+            let block: Element<'_, Message> = match block {
+                Block::Heading(level, heading) => text(heading).size(font_size * (2.0 - 0.2 * *level as f32)).into(),
+                Block::Paragraph { text: paragraph, quoted: false } => text(paragraph).size(font_size).into(),
+                Block::Paragraph { text: paragraph, quoted: true } => {
+                    row![text("│").size(font_size), text(paragraph).size(font_size)].spacing(8).into()
+                },
+                Block::Item { depth, marker, text: item } => row![
+                    Space::with_width(Length::Fixed(*depth as f32 * font_size * 1.5)),
+                    text(marker).size(font_size).width(Length::Fixed(font_size * 1.5)),
+                    text(item).size(font_size)]
+                    .into(),
+                Block::Code(code) => container(text(code).font(Font::MONOSPACE).size(font_size))
+                    .padding(8)
+                    .width(Length::Fill)
+                    .style(iced::theme::Container::Box)
+                    .into(),
+                Block::Rule => horizontal_rule(1).into(),
+            };
+
+            blocks.push(block)
+        });
+
+        scrollable(container(blocks).padding(10).width(Length::Fill))
+            .height(Length::Fill)
+            .into()
+    }
+}
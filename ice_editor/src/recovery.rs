@@ -0,0 +1,104 @@
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+    path::{Path, PathBuf},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::Error;
+
+/// the unsaved text of a buffer, mirrored so a crash doesn't take it along
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Recovered {
+    pub path: PathBuf,
+    pub text: String,
+}
+
+/// copies live in the OS cache dir, they're only worth anything until the next start
+fn dir() -> Option<PathBuf> {
+    dirs::cache_dir().map(|dir| dir.join("crab").join("recovery"))
+}
+
+/// where the copy for `path` goes, named by a hash so any path makes a valid file name
+fn file(path: &Path) -> Option<PathBuf> {
+    let mut hasher = DefaultHasher::new();
+    path.hash(&mut hasher);
+
+    dir().map(|dir| dir.join(format!("{:016x}.json", hasher.finish())))
+}
+
+/// mirror `text` as the recovery copy of `path`
+pub async fn write(path: PathBuf, text: String) -> Result<(), Error> {
+    let file = file(&path).ok_or_else(|| Error::IO {
+        action: "keep a recovery copy of",
+        path: path.clone(),
+        kind: std::io::ErrorKind::NotFound,
+    })?;
+    let json = serde_json::to_string(&Recovered { path: path.clone(), text }).map_err(|_| Error::IO {
+        action: "keep a recovery copy of",
+        path: path.clone(),
+        kind: std::io::ErrorKind::InvalidData,
+    })?;
+
+    if let Some(dir) = file.parent() {
+        tokio::fs::create_dir_all(dir)
+            .await
+            .map_err(Error::io("keep a recovery copy of", &path))?;
+    }
+
+    tokio::fs::write(&file, json)
+        .await
+        .map_err(Error::io("keep a recovery copy of", &path))
+}
+
+/// drop the copy of `path`, once its text is saved or thrown away on purpose
+pub async fn remove(path: PathBuf) -> Result<(), Error> {
+    let Some(file) = file(&path) else {
+        return Ok(());
+    };
+
+    match tokio::fs::remove_file(file).await {
+        Err(error) if error.kind() != std::io::ErrorKind::NotFound => {
+            Err(Error::io("remove the recovery copy of", &path)(error))
+        },
+        _ => Ok(()),
+    }
+}
+
+/// copies newer than their files, or whose files are gone
+///
+/// the rest are outdated or unreadable, and removed as they're found
+pub async fn find() -> Vec<Recovered> {
+    let Some(dir) = dir() else {
+        return Vec::new();
+    };
+    let Ok(mut entries) = tokio::fs::read_dir(dir).await else {
+        return Vec::new();
+    };
+    let mut found = Vec::new();
+
+    while let Ok(Some(entry)) = entries.next_entry().await {
+        let file = entry.path();
+        let copied = entry.metadata().await.and_then(|metadata| metadata.modified()).ok();
+        let recovered = tokio::fs::read_to_string(&file)
+            .await
+            .ok()
+            .and_then(|json| serde_json::from_str::<Recovered>(&json).ok());
+
+        let Some(recovered) = recovered else {
+            let _ = tokio::fs::remove_file(&file).await;
+            continue;
+        };
+
+        let saved = tokio::fs::metadata(&recovered.path).await.and_then(|metadata| metadata.modified()).ok();
+        match (copied, saved) {
+            (Some(copied), Some(saved)) if saved >= copied => {
+                let _ = tokio::fs::remove_file(&file).await;
+            },
+            _ => found.push(recovered),
+        }
+    }
+
+    found.sort_by(|a, b| a.path.cmp(&b.path));
+    found
+}
@@ -0,0 +1,71 @@
+use std::{path::PathBuf, time::{Duration, Instant}};
+use rhai::{Dynamic, Engine, EvalAltResult, Scope};
+
+use crate::settings::config_file;
+
+/// longest a script may run before it's stopped
+const TIME_LIMIT: Duration = Duration::from_secs(2);
+const EXTENSION: &str = "rhai";
+
+/// scripts live in `scripts` next to the settings file
+fn dir() -> Option<PathBuf> {
+    config_file("scripts")
+}
+
+/// names of the user's scripts, sorted, empty if there's no scripts dir
+pub async fn list() -> Vec<String> {
+    let Some(dir) = dir() else {
+        return Vec::new();
+    };
+    let Ok(mut entries) = tokio::fs::read_dir(dir).await else {
+        return Vec::new();
+    };
+    let mut names = Vec::new();
+
+    while let Ok(Some(entry)) = entries.next_entry().await {
+        let path = entry.path();
+
+        if path.extension().is_some_and(|extension| extension == EXTENSION) {
+            if let Some(name) = path.file_stem().and_then(|name| name.to_str()) {
+                names.push(name.to_owned());
+            }
+        }
+    }
+
+    names.sort();
+    names
+}
+
+/// run the script `name` and return the string it evaluates to
+///
+/// the script sees the buffer as `text`, the selection as `selection` and
+/// whether there is one as `has_selection`; the error is what to tell the user
+pub async fn run(name: String, text: String, selection: Option<String>) -> Result<String, String> {
+    let path = dir()
+        .map(|dir| dir.join(format!("{name}.{EXTENSION}")))
+        .ok_or_else(|| String::from("there's no config directory to keep scripts in"))?;
+    let source = tokio::fs::read_to_string(&path)
+        .await
+        .map_err(|error| format!("couldn't read {}: {error}", path.display()))?;
+
+    // it runs on an executor thread, so a runaway loop is cut off instead of tying one up
+    let mut engine = Engine::new();
+    let started = Instant::now();
+    engine.on_progress(move |_| (started.elapsed() > TIME_LIMIT).then_some(Dynamic::UNIT));
+
+    let mut scope = Scope::new();
+    scope.push("has_selection", selection.is_some());
+    scope.push("selection", selection.unwrap_or_default());
+    scope.push("text", text);
+
+    let result = engine.eval_with_scope::<Dynamic>(&mut scope, &source).map_err(|error| match *error {
+        EvalAltResult::ErrorTerminated(..) => {
+            format!("{name} ran for over {} seconds and was stopped", TIME_LIMIT.as_secs())
+        },
+        error => format!("{name}: {error}"),
+    })?;
+
+    result
+        .into_string()
+        .map_err(|type_name| format!("{name} returned {type_name} instead of a string"))
+}
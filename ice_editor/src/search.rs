@@ -0,0 +1,278 @@
+use std::ops::Range;
+use regex::{Regex, RegexBuilder};
+
+/// state of the find/replace bar
+#[derive(Debug, Clone, Default)]
+pub struct Search {
+    pub visible: bool,
+    pub query: String,
+    pub replacement: String,
+    pub case_sensitive: bool,
+    pub regex: bool, // off by default, so `.` and `(` mean what they say
+    error: Option<String>, // why the query isn't a valid regex
+    matches: Vec<Range<usize>>,
+    starts: Vec<usize>, // every offset the query matches at, overlapping ones too
+    searched: Option<Searched>,
+    current: Option<usize>, // index of the match stepped onto last
+}
+
+/// what the matches were found for
+#[derive(Debug, Clone, PartialEq)]
+struct Searched {
+    buffer: usize,
+    revision: usize,
+    query: String,
+    case_sensitive: bool,
+    regex: bool,
+}
+
+/// a query, ready to run on some text
+#[derive(Debug, Clone)]
+pub enum Pattern {
+    Text { query: String, case_sensitive: bool },
+    Regex(Regex),
+}
+
+impl Pattern {
+    /// the query as plain text, or compiled as a regex
+    pub fn new(query: &str, case_sensitive: bool, regex: bool) -> Result<Self, regex::Error> {
+        if !regex {
+            return Ok(Pattern::Text { query: query.to_owned(), case_sensitive });
+        }
+
+        RegexBuilder::new(query)
+            .case_insensitive(!case_sensitive)
+            .size_limit(1 << 20) // a runaway pattern errors out instead of hanging
+            .build()
+            .map(Pattern::Regex)
+    }
+
+    /// every non-overlapping match in `text`, in order
+    ///
+    /// empty regex matches are skipped, there would be nothing to select
+    pub fn find_all(&self, text: &str) -> Vec<Range<usize>> {
+        match self {
+            Pattern::Text { query, case_sensitive } => find_all(text, query, *case_sensitive),
+            Pattern::Regex(regex) if regex.as_str().is_empty() => Vec::new(),
+            Pattern::Regex(regex) => regex
+                .find_iter(text)
+                .map(|found| found.range())
+                .filter(|range| !range.is_empty())
+                .collect(),
+        }
+    }
+
+    /// replace every match, a regex replacement can refer to groups as `$1` or `${name}`
+    pub fn replace_all(&self, text: &str, replacement: &str) -> String {
+        match self {
+            Pattern::Text { query, case_sensitive } => replace_all(text, query, replacement, *case_sensitive),
+            Pattern::Regex(regex) => regex.replace_all(text, replacement).into_owned(),
+        }
+    }
+}
+
+impl Search {
+    /// whether the matches were found in this exact text, with the current query
+    pub fn is_fresh(&self, buffer: usize, revision: usize) -> bool {
+        self.searched.as_ref().is_some_and(|searched| {
+            searched.buffer == buffer
+                && searched.revision == revision
+                && searched.query == self.query
+                && searched.case_sensitive == self.case_sensitive
+                && searched.regex == self.regex
+        })
+    }
+
+    /// why the query can't be searched for, shown in the search bar
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+
+    /// find the matches again, if the query or the text changed
+    pub fn refresh(&mut self, buffer: usize, revision: usize, text: &str) {
+        if self.is_fresh(buffer, revision) {
+            return;
+        }
+
+        let searched = Searched {
+            buffer,
+            revision,
+            query: self.query.clone(),
+            case_sensitive: self.case_sensitive,
+            regex: self.regex,
+        };
+        self.current = None;
+        self.error = None;
+
+        if self.regex {
+            self.starts.clear();
+            self.matches = match Pattern::new(&self.query, self.case_sensitive, true) {
+                Ok(pattern) => pattern.find_all(text),
+                Err(error) => {
+                    self.error = Some(regex_error(&error));
+                    Vec::new()
+                },
+            };
+            self.searched = Some(searched);
+            return;
+        }
+
+        // typing on only narrows it down: a longer query matches where the shorter one did
+        let narrowed = self.searched.as_ref().is_some_and(|previous| {
+            previous.buffer == buffer
+                && previous.revision == revision
+                && previous.case_sensitive == self.case_sensitive
+                && !previous.regex
+                && !previous.query.is_empty()
+                && self.query.starts_with(&previous.query)
+        });
+
+        let (query, case_sensitive) = (&self.query, self.case_sensitive);
+        self.starts = if query.is_empty() {
+            Vec::new()
+        } else if narrowed {
+            self.starts.iter().copied().filter(|&start| match_at(text, start, query, case_sensitive).is_some()).collect()
+        } else {
+            text.char_indices()
+                .map(|(start, _)| start)
+                .filter(|&start| match_at(text, start, query, case_sensitive).is_some())
+                .collect()
+        };
+
+        // drop the ones overlapping an earlier match
+        self.matches.clear();
+        for &start in &self.starts {
+            if self.matches.last().is_some_and(|last| start < last.end) {
+                continue;
+            }
+
+            if let Some(end) = match_at(text, start, query, case_sensitive) {
+                self.matches.push(start..end);
+            }
+        }
+
+        self.searched = Some(searched);
+    }
+
+    /// which match was stepped onto and how many there are, for this buffer
+    ///
+    /// the text may have moved on since, the count catches up once typing pauses
+    pub fn count(&self, buffer: usize) -> Option<(Option<usize>, usize)> {
+        match &self.searched {
+            Some(searched) if searched.buffer == buffer => Some((self.current, self.matches.len())),
+            _ => None,
+        }
+    }
+
+    /// the next match starting at or after byte offset `from`, or the last one
+    /// ending before it, wrapping around
+    pub fn step(&mut self, from: usize, backwards: bool) -> Option<Range<usize>> {
+        let index = if backwards {
+            self.matches.iter().rposition(|found| found.end < from).or(self.matches.len().checked_sub(1))
+        } else {
+            self.matches.iter().position(|found| found.start >= from).or((!self.matches.is_empty()).then_some(0))
+        }?;
+
+        self.current = Some(index);
+        self.matches.get(index).cloned()
+    }
+}
+
+/// every non-overlapping match of `query`, in order
+pub fn find_all(text: &str, query: &str, case_sensitive: bool) -> Vec<Range<usize>> {
+    let mut matches = Vec::new();
+
+    if query.is_empty() {
+        return matches;
+    }
+
+    let mut next = 0;
+    for (start, _) in text.char_indices() {
+        if start < next {
+            continue;
+        }
+
+        if let Some(end) = match_at(text, start, query, case_sensitive) {
+            matches.push(start..end);
+            next = end;
+        }
+    }
+
+    matches
+}
+
+/// replace every match of `query` with `replacement`
+pub fn replace_all(text: &str, query: &str, replacement: &str, case_sensitive: bool) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut last = 0;
+
+    for range in find_all(text, query, case_sensitive) {
+        result.push_str(&text[last..range.start]);
+        result.push_str(replacement);
+        last = range.end;
+    }
+
+    result.push_str(&text[last..]);
+    result
+}
+
+/// the gist of a regex error, its full text spans several lines
+fn regex_error(error: &regex::Error) -> String {
+    match error {
+        regex::Error::Syntax(message) => {
+            let reason = message.lines().filter(|line| line.starts_with("error:")).last();
+            reason.map_or_else(|| String::from("invalid regex"), |line| line.replacen("error:", "invalid regex:", 1))
+        },
+        regex::Error::CompiledTooBig(_) => String::from("regex is too big"),
+        _ => String::from("invalid regex"),
+    }
+}
+
+/// byte offset where `query` ends if it matches at `start`
+fn match_at(text: &str, start: usize, query: &str, case_sensitive: bool) -> Option<usize> {
+    let mut haystack = text[start..].char_indices();
+
+    for wanted in query.chars() {
+        let (_, found) = haystack.next()?;
+
+        let equal = if case_sensitive {
+            found == wanted
+        } else {
+            found.to_lowercase().eq(wanted.to_lowercase())
+        };
+
+        if !equal {
+            return None;
+        }
+    }
+
+    Some(haystack.next().map_or(text.len(), |(offset, _)| start + offset))
+}
+
+/// `(line, column)` of a byte offset, with the column counted in chars
+pub fn position(text: &str, offset: usize) -> (usize, usize) {
+    let before = &text[..offset];
+    let line = before.matches('\n').count();
+    let line_start = before.rfind('\n').map_or(0, |newline| newline + 1);
+
+    (line, before[line_start..].chars().count())
+}
+
+/// byte offset of a `(line, column)` position, clamped to the text
+pub fn offset(text: &str, (line, column): (usize, usize)) -> usize {
+    let mut start = 0;
+
+    for _ in 0..line {
+        match text[start..].find('\n') {
+            Some(newline) => start += newline + 1,
+            None => return text.len(),
+        }
+    }
+
+    let line_text = text[start..].split('\n').next().unwrap_or("");
+
+    start + line_text
+        .char_indices()
+        .nth(column)
+        .map_or(line_text.len(), |(index, _)| index)
+}
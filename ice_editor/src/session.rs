@@ -0,0 +1,55 @@
+use std::path::PathBuf;
+use serde::{Deserialize, Serialize};
+
+use crate::{load_file, settings, Error, LoadedFile};
+
+/// the tabs that were open when the editor last quit
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Session {
+    pub paths: Vec<PathBuf>,
+    pub active: usize, // index into `paths`
+}
+
+impl Session {
+    /// the last session, if there is a readable one
+    pub fn load() -> Option<Self> {
+        let json = std::fs::read_to_string(settings::config_file("session.json")?).ok()?;
+        serde_json::from_str(&json).ok()
+    }
+
+    /// write the session file next to the settings
+    pub async fn save(self) -> Result<(), Error> {
+        let path = settings::config_file("session.json").ok_or_else(|| Error::IO {
+            action: "save the session to",
+            path: PathBuf::from("the config directory"),
+            kind: std::io::ErrorKind::NotFound,
+        })?;
+        let json = serde_json::to_string_pretty(&self).map_err(|_| Error::IO {
+            action: "save the session to",
+            path: path.clone(),
+            kind: std::io::ErrorKind::InvalidData,
+        })?;
+
+        if let Some(dir) = path.parent() {
+            tokio::fs::create_dir_all(dir)
+                .await
+                .map_err(Error::io("save the session to", dir))?;
+        }
+
+        tokio::fs::write(&path, json)
+            .await
+            .map_err(Error::io("save the session to", &path))
+    }
+
+    /// read every file of the session, a missing one only fails on its own
+    pub async fn restore(self, max_size: u64) -> (Vec<Result<LoadedFile, Error>>, usize) {
+        let mut files = Vec::new();
+
+        for path in self.paths {
+            files.push(load_file(path, max_size).await);
+        }
+
+        (files, self.active)
+    }
+}
@@ -0,0 +1,275 @@
+use std::{collections::{BTreeMap, HashMap, VecDeque}, path::{Path, PathBuf}};
+use serde::{Deserialize, Serialize};
+use iced::Theme;
+
+use crate::{highlight::{Language, SyntaxTheme}, Error, DEFAULT_FONT_SIZE, MAX_FONT_SIZE, MIN_FONT_SIZE};
+
+/// overrides for one file type, what's unset comes from the global settings
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Profile {
+    pub tab_width: Option<usize>,
+    pub use_spaces: Option<bool>,
+    pub word_wrap: Option<bool>,
+    pub trim_whitespace: Option<bool>,
+    pub final_newline: Option<bool>,
+    pub auto_indent: Option<bool>,
+}
+
+/// what a buffer is actually edited with, once its profile is applied
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Effective {
+    pub tab_width: usize,
+    pub use_spaces: bool,
+    pub word_wrap: bool,
+    pub trim_whitespace: bool,
+    pub final_newline: bool,
+    pub auto_indent: bool,
+}
+
+impl std::fmt::Display for Effective {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let on = |value| if value { "on" } else { "off" };
+
+        write!(
+            f,
+            "tab width {}, {}, wrap {}, trim {}, final newline {}, auto-indent {}",
+            self.tab_width,
+            if self.use_spaces { "spaces" } else { "tabs" },
+            on(self.word_wrap),
+            on(self.trim_whitespace),
+            on(self.final_newline),
+            on(self.auto_indent),
+        )
+    }
+}
+
+/// editor settings persisted between launches
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub theme: ThemeName,
+    pub syntax_theme: SyntaxTheme, // colors of the code, apart from the UI
+    pub font_size: f32,
+    pub last_open_path: Option<PathBuf>,
+    pub recent: VecDeque<PathBuf>,
+    pub pinned: Vec<PathBuf>, // in the order of the quick-access bar
+    pub line_numbers: bool,
+    pub autosave_secs: Option<u64>, // off when unset
+    pub recovery_secs: Option<u64>, // how often dirty buffers are mirrored to the cache dir, off when unset
+    pub tab_width: usize,
+    pub use_spaces: bool,
+    pub max_file_size_mb: u64, // bigger files are refused
+    pub light_file_size_mb: Option<u64>, // bigger files are offered a read-only mode, never when unset
+    pub watch_files: bool,
+    pub reload_on_focus: bool, // check for changes on disk when the window is focused again
+    pub word_wrap: bool,
+    pub trim_whitespace: bool, // on explicit saves, autosave leaves lines alone
+    pub final_newline: bool,
+    pub cursors: HashMap<PathBuf, (usize, usize)>, // last cursor position per file, 0-based
+    pub keybindings: BTreeMap<String, String>, // combo -> command id, on top of the defaults
+    pub auto_indent: bool,
+    pub profiles: BTreeMap<String, Profile>, // by file extension without the dot, on top of the above
+    pub smart_home: bool, // Home goes to the first non-blank char before the start of the line
+    pub date_format: String, // chrono strftime syntax
+    pub auto_close: bool, // brackets and quotes
+    pub wrap_selection: bool, // typing a bracket or quote over a selection surrounds it instead of replacing it
+    pub minimap: bool,
+    pub zen_maximize: bool, // zen mode maximizes the window too
+    pub clipboard_history: usize, // copies and cuts kept for ctrl+shift+v, none when 0
+    pub indent_guides: bool,
+    pub show_rulers: bool,
+    pub rulers: Vec<usize>, // columns to draw a line at, like 80 and 120
+    pub scroll_past_end: bool,
+    pub scroll_past_end_screens: f32, // how far past the last line, in window heights
+    pub font: Option<String>, // family name, the default monospace when unset
+    pub restore_session: bool, // reopen the tabs from last time
+    pub show_whitespace: bool,
+    pub title_format: String, // with {dirty}, {filename}, {dir} and {app}
+    pub languages: HashMap<PathBuf, Language>, // picked by hand, over the detected one
+    pub format_on_save: bool,
+    pub formatters: BTreeMap<String, String>, // extension -> command reading stdin and printing the result
+    pub external_command: Option<String>, // `{path}` is the file, like "rustfmt {path}"
+    pub backup_suffix: Option<String>, // copy the old file to `<name><suffix>` before saving, off when unset
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            theme: ThemeName::Dark,
+            syntax_theme: SyntaxTheme::default(),
+            font_size: DEFAULT_FONT_SIZE,
+            last_open_path: None,
+            recent: VecDeque::new(),
+            pinned: Vec::new(),
+            line_numbers: true,
+            autosave_secs: None,
+            recovery_secs: Some(5),
+            tab_width: 4,
+            use_spaces: false,
+            max_file_size_mb: 50,
+            light_file_size_mb: Some(10),
+            watch_files: true,
+            reload_on_focus: false,
+            word_wrap: true,
+            trim_whitespace: false,
+            final_newline: false,
+            cursors: HashMap::new(),
+            keybindings: BTreeMap::new(),
+            auto_indent: true,
+            profiles: BTreeMap::new(),
+            smart_home: true,
+            date_format: String::from(DEFAULT_DATE_FORMAT),
+            auto_close: true,
+            wrap_selection: true,
+            minimap: false,
+            zen_maximize: false,
+            clipboard_history: 10,
+            indent_guides: false,
+            show_rulers: false,
+            rulers: vec![80],
+            scroll_past_end: false,
+            scroll_past_end_screens: 0.5,
+            font: None,
+            restore_session: false,
+            show_whitespace: false,
+            title_format: String::from("{dirty}{filename} — {app}"),
+            languages: HashMap::new(),
+            backup_suffix: None,
+            external_command: None,
+            format_on_save: false,
+            formatters: BTreeMap::from([(String::from("rs"), String::from("rustfmt --edition 2021"))]),
+        }
+    }
+}
+
+/// ISO-8601 with the local offset
+pub const DEFAULT_DATE_FORMAT: &str = "%Y-%m-%dT%H:%M:%S%:z";
+
+/// serializable stand-in for `iced::Theme`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ThemeName {
+    Dark,
+    Light,
+}
+
+impl ThemeName {
+    pub fn toggled(self) -> Self {
+        match self {
+            ThemeName::Dark => ThemeName::Light,
+            ThemeName::Light => ThemeName::Dark,
+        }
+    }
+}
+
+impl From<ThemeName> for Theme {
+    fn from(name: ThemeName) -> Self {
+        match name {
+            ThemeName::Dark => Theme::Dark,
+            ThemeName::Light => Theme::Light,
+        }
+    }
+}
+
+impl Settings {
+    /// load the settings file, falling back to defaults if it is missing or corrupt
+    pub fn load() -> Self {
+        let mut settings: Self = config_file("settings.json")
+            .and_then(|path| std::fs::read_to_string(path).ok())
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default();
+
+        // a hand-edited file may hold anything
+        if !(MIN_FONT_SIZE..=MAX_FONT_SIZE).contains(&settings.font_size) {
+            settings.font_size = DEFAULT_FONT_SIZE;
+        }
+
+        if settings.tab_width == 0 {
+            settings.tab_width = Self::default().tab_width;
+        }
+
+        for profile in settings.profiles.values_mut() {
+            profile.tab_width = profile.tab_width.filter(|&width| width > 0);
+        }
+
+        settings
+    }
+
+    /// the extension and profile that apply to `path`, "rs", ".rs" and "RS" all match
+    pub fn profile(&self, path: Option<&Path>) -> Option<(&str, &Profile)> {
+        let extension = path?.extension()?.to_str()?;
+
+        self.profiles
+            .iter()
+            .find(|(key, _)| is_extension(key, extension))
+            .map(|(key, profile)| (key.as_str(), profile))
+    }
+
+    /// the profile that applies to `path`, to change it in place
+    pub fn profile_mut(&mut self, path: Option<&Path>) -> Option<&mut Profile> {
+        let extension = path?.extension()?.to_str()?;
+
+        self.profiles
+            .iter_mut()
+            .find(|(key, _)| is_extension(key, extension))
+            .map(|(_, profile)| profile)
+    }
+
+    /// the settings a file with `path` is edited with, its profile over the global ones
+    pub fn effective(&self, path: Option<&Path>) -> Effective {
+        let profile = self.profile(path).map(|(_, profile)| profile.clone()).unwrap_or_default();
+
+        Effective {
+            tab_width: profile.tab_width.unwrap_or(self.tab_width),
+            use_spaces: profile.use_spaces.unwrap_or(self.use_spaces),
+            word_wrap: profile.word_wrap.unwrap_or(self.word_wrap),
+            trim_whitespace: profile.trim_whitespace.unwrap_or(self.trim_whitespace),
+            final_newline: profile.final_newline.unwrap_or(self.final_newline),
+            auto_indent: profile.auto_indent.unwrap_or(self.auto_indent),
+        }
+    }
+
+    /// the file size limit in bytes
+    pub fn max_file_size(&self) -> u64 {
+        self.max_file_size_mb.saturating_mul(1024 * 1024)
+    }
+
+    /// the size in bytes from which files are offered the read-only mode
+    pub fn light_file_size(&self) -> Option<u64> {
+        self.light_file_size_mb.map(|mb| mb.saturating_mul(1024 * 1024))
+    }
+
+    /// write the settings file, creating the config dir if needed
+    pub async fn save(self) -> Result<(), Error> {
+        let path = config_file("settings.json").ok_or_else(|| Error::IO {
+            action: "save settings to",
+            path: PathBuf::from("the config directory"),
+            kind: std::io::ErrorKind::NotFound,
+        })?;
+        let json = serde_json::to_string_pretty(&self).map_err(|_| Error::IO {
+            action: "save settings to",
+            path: path.clone(),
+            kind: std::io::ErrorKind::InvalidData,
+        })?;
+
+        if let Some(dir) = path.parent() {
+            tokio::fs::create_dir_all(dir)
+                .await
+                .map_err(Error::io("save settings to", dir))?;
+        }
+
+        tokio::fs::write(&path, json)
+            .await
+            .map_err(Error::io("save settings to", &path))
+    }
+}
+
+/// whether a profile key is for `extension`
+fn is_extension(key: &str, extension: &str) -> bool {
+    key.trim_start_matches('.').eq_ignore_ascii_case(extension)
+}
+
+/// settings and the like live in the OS config dir
+pub fn config_file(name: &str) -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("crab").join(name))
+}
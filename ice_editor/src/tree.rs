@@ -0,0 +1,111 @@
+use std::path::{Path, PathBuf};
+
+use crate::Error;
+
+/// a file or directory in the sidebar
+#[derive(Debug, Clone)]
+pub struct Node {
+    pub path: PathBuf,
+    pub name: String,
+    pub is_dir: bool,
+    pub expanded: bool,
+    pub children: Option<Vec<Node>>, // `None` until the directory is first expanded
+}
+
+impl Node {
+    /// a node for `path`, with nothing read yet
+    pub fn new(path: PathBuf, is_dir: bool) -> Self {
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| path.display().to_string());
+
+        Self { path, name, is_dir, expanded: false, children: None }
+    }
+
+    /// the node at `path` in this subtree
+    pub fn find_mut(&mut self, path: &Path) -> Option<&mut Node> {
+        if self.path == path {
+            return Some(self);
+        }
+
+        // only descend into the directory that can hold it
+        if !path.starts_with(&self.path) {
+            return None;
+        }
+
+        self.children.as_mut()?.iter_mut().find_map(|child| child.find_mut(path))
+    }
+
+    /// visible nodes in display order, with how deep each one is
+    pub fn rows(&self) -> Vec<(usize, &Node)> {
+        let mut rows = Vec::new();
+        self.collect_rows(0, &mut rows);
+        rows
+    }
+
+    fn collect_rows<'a>(&'a self, depth: usize, rows: &mut Vec<(usize, &'a Node)>) {
+        rows.push((depth, self));
+
+        if let (true, Some(children)) = (self.expanded, &self.children) {
+            for child in children {
+                child.collect_rows(depth + 1, rows);
+            }
+        }
+    }
+}
+
+/// the entries of a directory, directories first, each group by name
+pub async fn read_dir(path: PathBuf) -> Result<Vec<Node>, Error> {
+    let mut entries = tokio::fs::read_dir(&path)
+        .await
+        .map_err(Error::io("read folder", &path))?;
+    let mut nodes = Vec::new();
+
+    while let Some(entry) = entries.next_entry().await.map_err(Error::io("read folder", &path))? {
+        // an entry we can't stat is most likely gone already
+        let Ok(file_type) = entry.file_type().await else {
+            continue;
+        };
+
+        nodes.push(Node::new(entry.path(), file_type.is_dir()));
+    }
+
+    nodes.sort_by(|a, b| b.is_dir.cmp(&a.is_dir).then_with(|| a.name.to_lowercase().cmp(&b.name.to_lowercase())));
+
+    Ok(nodes)
+}
+
+/// files and folders that mark the top of a project
+const PROJECT_MARKERS: &[&str] = &["Cargo.toml", ".git", "package.json"];
+
+/// the nearest folder above `file` with a project marker in it, or the
+/// file's own folder if there's none
+pub async fn project_root(file: &Path) -> PathBuf {
+    let dir = file.parent().unwrap_or(file);
+
+    for ancestor in dir.ancestors() {
+        for marker in PROJECT_MARKERS {
+            if tokio::fs::try_exists(ancestor.join(marker)).await.unwrap_or(false) {
+                return ancestor.to_owned();
+            }
+        }
+    }
+
+    dir.to_owned()
+}
+
+/// ask for a folder to show in the sidebar, starting out in `start`
+pub async fn pick_folder(start: Option<PathBuf>) -> Result<PathBuf, Error> {
+    let dialog = rfd::AsyncFileDialog::new().set_title("Choose a folder...");
+    let dialog = match start {
+        Some(start) => dialog.set_directory(start),
+        None => dialog,
+    };
+
+    dialog
+        .pick_folder()
+        .await
+        .map(|handle| handle.path().to_owned())
+        .ok_or(Error::DialogClosed)
+}
@@ -0,0 +1,59 @@
+use std::path::Path;
+
+use crate::{highlight::Language, search};
+
+/// the formats a buffer is checked against
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Json,
+    Toml,
+}
+
+impl Format {
+    /// TOML has no highlighting, so it's told apart by the extension
+    pub fn of(path: Option<&Path>, language: Language) -> Option<Self> {
+        match path.and_then(Path::extension).and_then(|extension| extension.to_str()) {
+            Some("toml") => Some(Format::Toml),
+            _ if language == Language::Json => Some(Format::Json),
+            _ => None,
+        }
+    }
+}
+
+/// where a buffer stops parsing, 1-based like compiler output
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Problem {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+/// the latest check of the active buffer
+#[derive(Debug, Clone, Default)]
+pub struct Validation {
+    pub problem: Option<Problem>,
+    pub checked: Option<(usize, usize)>, // buffer id and revision the problem is for
+    pub pending: Option<(usize, usize)>, // being checked right now
+}
+
+/// parse `text` as `format`, on the executor's threads like the preview
+pub async fn check(format: Format, text: String) -> Option<Problem> {
+    match format {
+        Format::Json => {
+            let error = serde_json::from_str::<serde::de::IgnoredAny>(&text).err()?;
+            // the position is already in the fields, don't repeat it
+            let message = error.to_string();
+            let message = message.split(" at line ").next().unwrap_or(&message).to_owned();
+
+            Some(Problem { line: error.line().max(1), column: error.column().max(1), message })
+        },
+        Format::Toml => {
+            let error = text.parse::<toml::Table>().err()?;
+            let (line, column) = error
+                .span()
+                .map_or((0, 0), |span| search::position(&text, span.start.min(text.len())));
+
+            Some(Problem { line: line + 1, column: column + 1, message: error.message().to_owned() })
+        },
+    }
+}
@@ -0,0 +1,46 @@
+use std::path::PathBuf;
+use iced::{
+    futures::{channel::mpsc, SinkExt, StreamExt},
+    subscription, Subscription,
+};
+use notify::{EventKind, RecursiveMode, Watcher};
+
+use crate::Message;
+
+/// report when one of `paths` changes on disk
+///
+/// the subscription is keyed by the paths, so opening or closing a file
+/// restarts the watcher with the new set
+///
+/// saves rename a new file over the old one, which would end a watch on the
+/// file itself, so the directories holding them are watched instead
+pub fn files(paths: Vec<PathBuf>) -> Subscription<Message> {
+    subscription::channel(paths.clone(), 100, |mut output| async move {
+        let (sender, mut events) = mpsc::unbounded();
+
+        // keep the watcher alive for as long as the subscription runs
+        let _watcher = notify::recommended_watcher(move |event| {
+            let _ = sender.unbounded_send(event);
+        })
+        .map(|mut watcher| {
+            for dir in paths.iter().filter_map(|path| path.parent()) {
+                let _ = watcher.watch(dir, RecursiveMode::NonRecursive);
+            }
+
+            watcher
+        });
+
+        loop {
+            match events.next().await {
+                Some(Ok(event)) if matches!(event.kind, EventKind::Modify(_) | EventKind::Create(_)) => {
+                    for path in event.paths.into_iter().filter(|path| paths.contains(path)) {
+                        let _ = output.send(Message::FileChanged(path)).await;
+                    }
+                },
+                Some(_) => {},
+                // the watcher could not be created, nothing will ever come
+                None => std::future::pending::<()>().await,
+            }
+        }
+    })
+}